@@ -0,0 +1,64 @@
+// Chapter headings are short, so longer lines are always treated as text
+const MAX_CHAPTER_HEADING_LENGTH: usize = 60;
+
+/// Returns true if a line that stands on its own (between blank lines) looks
+/// like a chapter heading e.g. `CHAPTER IV`, `I.`, `XII` or `THE RED CIRCLE`
+pub fn is_chapter_heading(line: &str) -> bool {
+  let line = line.trim();
+
+  if line.is_empty() || line.chars().count() > MAX_CHAPTER_HEADING_LENGTH {
+    return false;
+  }
+
+  let first_word = line.split_whitespace().next().unwrap_or_default();
+  if first_word.eq_ignore_ascii_case("chapter") && line.len() > first_word.len() {
+    return true;
+  }
+
+  let numbering = line.strip_suffix('.').unwrap_or(line);
+  if is_roman_numeral(numbering) || (line.ends_with('.') && numbering.chars().all(|char| char.is_ascii_digit())) {
+    return !numbering.is_empty();
+  }
+
+  is_all_caps(line)
+}
+
+fn is_roman_numeral(text: &str) -> bool {
+  !text.is_empty() && text.len() <= 8 && text.chars().all(|char| "IVXLCDM".contains(char))
+}
+
+// A line with at least two letters, all of them upper case, that doesn't end
+// mid sentence
+fn is_all_caps(line: &str) -> bool {
+  let letter_count = line.chars().filter(|char| char.is_alphabetic()).count();
+
+  letter_count >= 2 && !line.chars().any(|char| char.is_lowercase()) && !line.ends_with(',') && !line.ends_with(';')
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::chapters::*;
+
+  #[test]
+  fn detects_chapter_keyword() {
+    assert!(is_chapter_heading("CHAPTER IV\n"));
+    assert!(is_chapter_heading("Chapter 4. The Return\n"));
+    assert!(!is_chapter_heading("Chapter\n"));
+  }
+
+  #[test]
+  fn detects_numbered_headings() {
+    assert!(is_chapter_heading("I.\n"));
+    assert!(is_chapter_heading("XII\n"));
+    assert!(is_chapter_heading("12.\n"));
+    assert!(!is_chapter_heading("12\n"));
+  }
+
+  #[test]
+  fn detects_all_caps_headings() {
+    assert!(is_chapter_heading("THE ADVENTURE OF THE SPECKLED BAND\n"));
+    assert!(!is_chapter_heading("“Go! Where to?”\n"));
+    assert!(!is_chapter_heading("A\n"));
+    assert!(!is_chapter_heading("HOLMES,\n"));
+  }
+}
@@ -0,0 +1,754 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::{fmt, path};
+
+use crate::error::{read_text_file, Error};
+use crate::hooks::BuiltinHook;
+use crate::render::OutputFormat;
+use crate::site::NavEntry;
+use crate::{DEFAULT_LANG, DEFAULT_OUTPUT_DIR, DEFAULT_SHORTCODES_DIR};
+
+/// Keys that can be set by a config file, environment variable or flag
+pub const CONFIG_KEYS: [&str; 27] = [
+  "input",
+  "output",
+  "output_format",
+  "lang",
+  "permalinks",
+  "chapters",
+  "reflow",
+  "strip_gutenberg",
+  "smart_typography",
+  "emoji",
+  "emoji_in_txt",
+  "definition_lists",
+  "abbreviations",
+  "superscript",
+  "subscript",
+  "template",
+  "shortcodes",
+  "fail_fast",
+  "title",
+  "base_url",
+  "author",
+  "description",
+  "favicon",
+  "copyright",
+  "nav",
+  "extra",
+  "hooks",
+];
+// Keys with lists or tables as values, which can only be set in a config file
+const FILE_ONLY_KEYS: [&str; 3] = ["nav", "extra", "hooks"];
+// Keys with true or false values, the only ones read as booleans from
+// environment variables
+const FLAG_KEYS: [&str; 12] = [
+  "permalinks",
+  "chapters",
+  "reflow",
+  "strip_gutenberg",
+  "smart_typography",
+  "emoji",
+  "emoji_in_txt",
+  "definition_lists",
+  "abbreviations",
+  "superscript",
+  "subscript",
+  "fail_fast",
+];
+/// Config files looked for when no `-c` is given, in order of preference
+pub const CONFIG_FILE_NAMES: [&str; 4] = ["rost_gen.toml", "rost_gen.yaml", "rost_gen.yml", "rost_gen.json"];
+// Config values that are paths, resolved against the directory of a
+// discovered config file
+const PATH_KEYS: [&str; 4] = ["input", "output", "template", "shortcodes"];
+// Environment variables are the key in upper case with this prefix e.g.
+// ROST_GEN_OUTPUT
+const ENV_PREFIX: &str = "ROST_GEN_";
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Config {
+  pub input: Option<String>,
+  pub output: Option<String>,
+  pub output_format: Option<OutputFormat>,
+  pub lang: Option<String>,
+  pub permalinks: Option<bool>,
+  pub chapters: Option<bool>,
+  pub reflow: Option<bool>,
+  pub strip_gutenberg: Option<bool>,
+  pub smart_typography: Option<bool>,
+  pub emoji: Option<bool>,
+  pub emoji_in_txt: Option<bool>,
+  pub definition_lists: Option<bool>,
+  pub abbreviations: Option<bool>,
+  pub superscript: Option<bool>,
+  pub subscript: Option<bool>,
+  pub template: Option<String>,
+  /// Directory with an html template for each shortcode
+  pub shortcodes: Option<String>,
+  pub fail_fast: Option<bool>,
+  pub title: Option<String>,
+  pub base_url: Option<String>,
+  pub author: Option<String>,
+  pub description: Option<String>,
+  pub favicon: Option<String>,
+  pub copyright: Option<String>,
+  pub nav: Option<Vec<NavEntry>>,
+  pub extra: Option<Map<String, Value>>,
+  pub hooks: Option<Vec<BuiltinHook>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ConfigFormat {
+  Json,
+  Toml,
+  Yaml,
+}
+
+impl ConfigFormat {
+  /// Returns None if the extension isn't one of .json, .toml, .yaml or .yml
+  pub fn from_path(path: &path::Path) -> Option<Self> {
+    match path.extension()?.to_str()?.to_lowercase().as_str() {
+      "json" => Some(ConfigFormat::Json),
+      "toml" => Some(ConfigFormat::Toml),
+      "yaml" | "yml" => Some(ConfigFormat::Yaml),
+      _ => None,
+    }
+  }
+}
+
+/// A problem found in a config file, with the position it was found at when
+/// known
+#[derive(Debug, PartialEq, Eq)]
+pub struct ConfigError {
+  pub line: Option<usize>,
+  pub column: Option<usize>,
+  pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    match (self.line, self.column) {
+      (Some(line), Some(column)) => write!(formatter, "line {line}, column {column}: {}", self.message),
+      (Some(line), None) => write!(formatter, "line {line}: {}", self.message),
+      _ => write!(formatter, "{}", self.message),
+    }
+  }
+}
+
+/// Where the value of a config key came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+  Default,
+  ConfigFile(String),
+  Environment(String),
+  CommandLine,
+}
+
+impl fmt::Display for Source {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Source::Default => write!(formatter, "default"),
+      Source::ConfigFile(config_path) => write!(formatter, "config file {config_path}"),
+      Source::Environment(variable) => write!(formatter, "environment variable {variable}"),
+      Source::CommandLine => write!(formatter, "command line"),
+    }
+  }
+}
+
+/// Config values merged from each layer, where later layers override earlier
+/// ones: defaults < config file < environment variables < command line
+pub struct LayeredConfig {
+  values: Map<String, Value>,
+  sources: HashMap<String, Source>,
+}
+
+impl LayeredConfig {
+  pub fn with_defaults() -> Self {
+    let defaults = Config {
+      output: Some(DEFAULT_OUTPUT_DIR.to_string()),
+      output_format: Some(OutputFormat::Html),
+      lang: Some(DEFAULT_LANG.to_string()),
+      permalinks: Some(false),
+      chapters: Some(false),
+      reflow: Some(false),
+      strip_gutenberg: Some(false),
+      smart_typography: Some(false),
+      emoji: Some(false),
+      emoji_in_txt: Some(false),
+      definition_lists: Some(false),
+      abbreviations: Some(false),
+      superscript: Some(false),
+      subscript: Some(false),
+      shortcodes: Some(DEFAULT_SHORTCODES_DIR.to_string()),
+      fail_fast: Some(false),
+      ..Default::default()
+    };
+
+    let mut layered_config = LayeredConfig {
+      values: Map::new(),
+      sources: HashMap::new(),
+    };
+    layered_config.merge(to_values(&defaults), Source::Default);
+    layered_config
+  }
+
+  /// Override existing values with the non null values of a layer
+  pub fn merge(&mut self, layer: Map<String, Value>, source: Source) {
+    for (key, value) in layer {
+      if !value.is_null() {
+        self.sources.insert(key.clone(), source.clone());
+        self.values.insert(key, value);
+      }
+    }
+  }
+
+  /// Override existing values with `ROST_GEN_*` variables
+  pub fn merge_env_vars(&mut self, env_vars: impl Iterator<Item = (String, String)>) {
+    let env_vars: HashMap<String, String> = env_vars.collect();
+
+    for key in CONFIG_KEYS.iter().filter(|key| !FILE_ONLY_KEYS.contains(key)) {
+      let variable = format!("{ENV_PREFIX}{}", key.to_uppercase());
+      if let Some(value) = env_vars.get(&variable) {
+        let value = match value.to_lowercase().as_str() {
+          "true" if FLAG_KEYS.contains(key) => Value::Bool(true),
+          "false" if FLAG_KEYS.contains(key) => Value::Bool(false),
+          _ => Value::String(value.clone()),
+        };
+        self.merge(
+          Map::from_iter([(key.to_string(), value)]),
+          Source::Environment(variable),
+        );
+      }
+    }
+  }
+
+  pub fn config(&self) -> Result<Config, String> {
+    serde_json::from_value(Value::Object(self.values.clone())).map_err(|_| {
+      // Check each value on its own to say which one is wrong and where it
+      // came from
+      let mut messages = Vec::new();
+      for (key, value) in &self.values {
+        let single_value = Map::from_iter([(key.clone(), value.clone())]);
+        if let Err(error) = serde_json::from_value::<Config>(Value::Object(single_value)) {
+          let source = self.sources.get(key).unwrap_or(&Source::Default);
+          messages.push(format!("{key} ({source}): {error}"));
+        }
+      }
+      messages.join("\n  ")
+    })
+  }
+
+  /// Each key with its effective value and where it came from, one per line
+  pub fn describe(&self) -> String {
+    let mut description = String::new();
+
+    for key in CONFIG_KEYS {
+      match (self.values.get(key), self.sources.get(key)) {
+        (Some(value), Some(source)) => description.push_str(&format!("{key} = {value} ({source})\n")),
+        _ => description.push_str(&format!("{key} is not set\n")),
+      }
+    }
+
+    description
+  }
+}
+
+pub fn to_values(config: &Config) -> Map<String, Value> {
+  match serde_json::to_value(config) {
+    Ok(Value::Object(values)) => values,
+    _ => Map::new(),
+  }
+}
+
+/// Read the values set in a config file, using the format given or the one
+/// matching the file's extension
+pub fn read_config_file(config_path: &str, format: Option<ConfigFormat>) -> Result<Map<String, Value>, Error> {
+  let path = path::Path::new(config_path);
+  let config_error = |message: &str| Error::Config {
+    path: Some(path.to_path_buf()),
+    message: message.to_string(),
+  };
+
+  if !path.exists() {
+    return Err(config_error("No file found"));
+  }
+
+  if !path.is_file() {
+    return Err(config_error("It is not a file"));
+  }
+
+  let format = format.or_else(|| ConfigFormat::from_path(path)).ok_or_else(|| {
+    config_error("Only .json, .toml, .yaml or .yml files are accepted, use --config-format to read other files")
+  })?;
+
+  let contents = read_text_file("read config file", path)?;
+  let config = parse_config(&contents, format).map_err(|errors| {
+    let messages: Vec<String> = errors.iter().map(ToString::to_string).collect();
+    config_error(&messages.join("\n  "))
+  })?;
+  Ok(to_values(&config))
+}
+
+/// Parse a config, reporting syntax errors, unknown keys and values of the
+/// wrong type
+pub fn parse_config(contents: &str, format: ConfigFormat) -> Result<Config, Vec<ConfigError>> {
+  // Read the keys first so every unknown key can be reported, not just the
+  // first one
+  let values = match parse_values(contents, format)? {
+    Value::Object(values) => values,
+    Value::Null => Map::new(),
+    _ => {
+      return Err(vec![ConfigError {
+        line: None,
+        column: None,
+        message: "expected a table of keys and values".to_string(),
+      }])
+    }
+  };
+
+  let unknown_key_errors: Vec<ConfigError> = values
+    .keys()
+    .filter(|key| !CONFIG_KEYS.contains(&key.as_str()))
+    .map(|key| unknown_key_error(contents, key))
+    .collect();
+  if !unknown_key_errors.is_empty() {
+    return Err(unknown_key_errors);
+  }
+
+  match format {
+    ConfigFormat::Json => serde_json::from_str(contents).map_err(|error| vec![json_error(&error)]),
+    ConfigFormat::Toml => toml::from_str(contents).map_err(|error| vec![toml_error(contents, &error)]),
+    ConfigFormat::Yaml => serde_yaml::from_str::<Option<Config>>(contents)
+      .map(Option::unwrap_or_default)
+      .map_err(|error| vec![yaml_error(&error)]),
+  }
+}
+
+// Parse the config without checking its keys or value types
+fn parse_values(contents: &str, format: ConfigFormat) -> Result<Value, Vec<ConfigError>> {
+  let result = match format {
+    ConfigFormat::Json => serde_json::from_str(contents).map_err(|error| json_error(&error)),
+    ConfigFormat::Toml => toml::from_str(contents).map_err(|error| toml_error(contents, &error)),
+    ConfigFormat::Yaml => serde_yaml::from_str(contents).map_err(|error| yaml_error(&error)),
+  };
+  result.map_err(|error| vec![error])
+}
+
+// The position is reported separately, so it's removed from the message
+fn json_error(error: &serde_json::Error) -> ConfigError {
+  let suffix = format!(" at line {} column {}", error.line(), error.column());
+  let message = error.to_string();
+  ConfigError {
+    line: Some(error.line()),
+    column: Some(error.column()),
+    message: message.strip_suffix(&suffix).unwrap_or(&message).to_string(),
+  }
+}
+
+fn toml_error(contents: &str, error: &toml::de::Error) -> ConfigError {
+  let (line, column) = match error.span() {
+    Some(span) => {
+      let before_error = &contents[..span.start];
+      let line_start = before_error.rfind('\n').map_or(0, |newline| newline + 1);
+      (
+        Some(before_error.matches('\n').count() + 1),
+        Some(before_error[line_start..].chars().count() + 1),
+      )
+    }
+    None => (None, None),
+  };
+
+  ConfigError {
+    line,
+    column,
+    message: error.message().trim_end().to_string(),
+  }
+}
+
+fn yaml_error(error: &serde_yaml::Error) -> ConfigError {
+  let location = error.location();
+  let message = error.to_string();
+  let message = match message.find(" at line ") {
+    Some(position) if location.is_some() => message[..position].to_string(),
+    _ => message,
+  };
+
+  ConfigError {
+    line: location.as_ref().map(|location| location.line()),
+    column: location.as_ref().map(|location| location.column()),
+    message,
+  }
+}
+
+fn unknown_key_error(contents: &str, key: &str) -> ConfigError {
+  let mut message = format!("unknown key '{key}'");
+  if let Some(suggestion) = suggest_key(key) {
+    message.push_str(&format!(", did you mean '{suggestion}'?"));
+  }
+
+  // The first place the key is written, quoted or not, anywhere in a line
+  // e.g. in an inline `{"ouptut": "x"}` object
+  let position = contents
+    .lines()
+    .enumerate()
+    .find_map(|(line_index, line)| key_column(line, key).map(|column| (line_index + 1, column)));
+
+  ConfigError {
+    line: position.map(|(line, _)| line),
+    column: position.map(|(_, column)| column),
+    message,
+  }
+}
+
+// A key is followed by `:` or `=`, and is either quoted or starts after a
+// space, `{` or `,`
+// returns the column of the key, or of its opening quote
+fn key_column(line: &str, key: &str) -> Option<usize> {
+  line.match_indices(key).find_map(|(start, _)| {
+    let before = line[..start].chars().next_back();
+    let after = &line[(start + key.len())..];
+    let (column, after) = match before {
+      Some(quote @ ('"' | '\'')) => (start, after.strip_prefix(quote)?),
+      None | Some(' ' | '\t' | '{' | ',') => (start + 1, after),
+      _ => return None,
+    };
+    after.trim_start().starts_with([':', '=']).then_some(column)
+  })
+}
+
+/// Returns the config key closest to a misspelled one, if any is close enough
+pub fn suggest_key(key: &str) -> Option<&'static str> {
+  let key = key.to_lowercase().replace('-', "_");
+  CONFIG_KEYS
+    .iter()
+    .map(|config_key| (edit_distance(&key, config_key), *config_key))
+    .filter(|(distance, config_key)| *distance <= (config_key.len() / 3).max(1))
+    .min_by_key(|(distance, _)| *distance)
+    .map(|(_, config_key)| config_key)
+}
+
+// The number of insertions, deletions, substitutions or swaps of adjacent
+// characters needed to turn one string into the other
+fn edit_distance(from: &str, to: &str) -> usize {
+  let from: Vec<char> = from.chars().collect();
+  let to: Vec<char> = to.chars().collect();
+  let mut distances = vec![vec![0; to.len() + 1]; from.len() + 1];
+
+  for (i, row) in distances.iter_mut().enumerate() {
+    row[0] = i;
+  }
+  distances[0] = (0..=to.len()).collect();
+
+  for i in 1..=from.len() {
+    for j in 1..=to.len() {
+      let substitution_cost = usize::from(from[i - 1] != to[j - 1]);
+      let mut distance = (distances[i - 1][j] + 1)
+        .min(distances[i][j - 1] + 1)
+        .min(distances[i - 1][j - 1] + substitution_cost);
+      if i > 1 && j > 1 && from[i - 1] == to[j - 2] && from[i - 2] == to[j - 1] {
+        distance = distance.min(distances[i - 2][j - 2] + 1);
+      }
+      distances[i][j] = distance;
+    }
+  }
+
+  distances[from.len()][to.len()]
+}
+
+/// Look for a config file in the directory and each of its parents, returning
+/// the first one found
+pub fn discover_config_file(start_dir: &path::Path) -> Option<path::PathBuf> {
+  start_dir.ancestors().find_map(|dir| {
+    CONFIG_FILE_NAMES
+      .iter()
+      .map(|file_name| dir.join(file_name))
+      .find(|config_path| config_path.is_file())
+  })
+}
+
+/// Make relative paths in the config values relative to the config file's
+/// directory instead of the current directory
+pub fn resolve_paths(values: &mut Map<String, Value>, config_dir: &path::Path) {
+  for key in PATH_KEYS {
+    if let Some(Value::String(value)) = values.get_mut(key) {
+      if path::Path::new(value).is_relative() {
+        *value = config_dir.join(&*value).to_string_lossy().to_string();
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs;
+
+  use crate::config::*;
+
+  fn layer(config: Config) -> Map<String, Value> { to_values(&config) }
+
+  #[test]
+  fn later_layers_override_earlier_layers() {
+    let mut layered_config = LayeredConfig::with_defaults();
+    layered_config.merge(
+      layer(Config {
+        input: Some("docs".to_string()),
+        output: Some("site".to_string()),
+        lang: Some("fr".to_string()),
+        ..Default::default()
+      }),
+      Source::ConfigFile("site.json".to_string()),
+    );
+    layered_config.merge_env_vars([("ROST_GEN_LANG".to_string(), "de".to_string())].into_iter());
+    layered_config.merge(
+      layer(Config {
+        output: Some("other_dir".to_string()),
+        ..Default::default()
+      }),
+      Source::CommandLine,
+    );
+
+    let config = layered_config.config().unwrap();
+
+    assert_eq!(config.input.as_deref(), Some("docs"));
+    assert_eq!(config.output.as_deref(), Some("other_dir"));
+    assert_eq!(config.lang.as_deref(), Some("de"));
+    assert_eq!(config.reflow, Some(false));
+  }
+
+  #[test]
+  fn parses_boolean_env_vars() {
+    let mut layered_config = LayeredConfig::with_defaults();
+    layered_config.merge_env_vars(
+      [
+        ("ROST_GEN_REFLOW".to_string(), "TRUE".to_string()),
+        ("ROST_GEN_UNKNOWN".to_string(), "true".to_string()),
+      ]
+      .into_iter(),
+    );
+
+    assert_eq!(layered_config.config().unwrap().reflow, Some(true));
+  }
+
+  #[test]
+  fn keeps_true_and_false_as_strings_for_text_keys() {
+    let mut layered_config = LayeredConfig::with_defaults();
+    layered_config.merge_env_vars(
+      [
+        ("ROST_GEN_TITLE".to_string(), "False".to_string()),
+        ("ROST_GEN_AUTHOR".to_string(), "true".to_string()),
+      ]
+      .into_iter(),
+    );
+
+    let config = layered_config.config().unwrap();
+    assert_eq!(config.title.as_deref(), Some("False"));
+    assert_eq!(config.author.as_deref(), Some("true"));
+  }
+
+  #[test]
+  fn describes_values_and_sources() {
+    let mut layered_config = LayeredConfig::with_defaults();
+    layered_config.merge_env_vars([("ROST_GEN_LANG".to_string(), "fr".to_string())].into_iter());
+
+    let description = layered_config.describe();
+
+    assert!(description.contains("input is not set\n"));
+    assert!(description.contains("output = \"./dist\" (default)\n"));
+    assert!(description.contains("lang = \"fr\" (environment variable ROST_GEN_LANG)\n"));
+  }
+
+  #[test]
+  fn parses_each_config_format() {
+    let json = parse_config("{\"input\": \"docs\", \"reflow\": true}", ConfigFormat::Json).unwrap();
+    let toml = parse_config("input = \"docs\"\nreflow = true\n", ConfigFormat::Toml).unwrap();
+    let yaml = parse_config("input: docs\nreflow: true\n", ConfigFormat::Yaml).unwrap();
+
+    for config in [json, toml, yaml] {
+      assert_eq!(config.input.as_deref(), Some("docs"));
+      assert_eq!(config.reflow, Some(true));
+    }
+    assert!(parse_config("input = ", ConfigFormat::Toml).is_err());
+  }
+
+  #[test]
+  fn parses_builtin_hooks() {
+    let config = parse_config(
+      "[[hooks]]\nname = \"banner\"\nhtml = \"<p>Beta</p>\"\n",
+      ConfigFormat::Toml,
+    )
+    .unwrap();
+    assert_eq!(
+      config.hooks,
+      Some(vec![BuiltinHook::Banner {
+        html: "<p>Beta</p>".to_string()
+      }])
+    );
+
+    let error = &parse_config("{\"hooks\": [{\"name\": \"sed\"}]}", ConfigFormat::Json).unwrap_err()[0];
+    assert!(error.message.starts_with("unknown variant `sed`"));
+  }
+
+  #[test]
+  fn reads_config_file_by_extension_or_format() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let config_path = temp_dir.path().join("site.conf");
+    fs::write(&config_path, "lang: fr\n").expect("Create test config");
+    let config_path = config_path.to_str().unwrap();
+
+    assert!(read_config_file(config_path, None).is_err());
+    assert_eq!(
+      read_config_file(config_path, Some(ConfigFormat::Yaml))
+        .unwrap()
+        .get("lang"),
+      Some(&Value::from("fr"))
+    );
+
+    temp_dir.close().expect("Delete test directory");
+  }
+
+  #[test]
+  fn discovers_config_file_in_parent_directory() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let nested_dir = temp_dir.path().join("content").join("posts");
+    fs::create_dir_all(&nested_dir).expect("Create test directory");
+    fs::write(temp_dir.path().join("rost_gen.json"), "{}").expect("Create test config");
+    fs::write(temp_dir.path().join("rost_gen.toml"), "").expect("Create test config");
+
+    assert_eq!(
+      discover_config_file(&nested_dir),
+      Some(temp_dir.path().join("rost_gen.toml"))
+    );
+
+    temp_dir.close().expect("Delete test directory");
+  }
+
+  #[test]
+  fn resolves_relative_paths_against_config_directory() {
+    let mut values = layer(Config {
+      input: Some("content".to_string()),
+      output: Some("/tmp/site".to_string()),
+      lang: Some("fr".to_string()),
+      ..Default::default()
+    });
+    resolve_paths(&mut values, path::Path::new("../site"));
+
+    assert_eq!(values.get("input"), Some(&Value::from("../site/content")));
+    assert_eq!(values.get("output"), Some(&Value::from("/tmp/site")));
+    assert_eq!(values.get("lang"), Some(&Value::from("fr")));
+  }
+
+  #[test]
+  fn suggests_misspelled_keys() {
+    assert_eq!(suggest_key("ouptut"), Some("output"));
+    assert_eq!(suggest_key("smart-typography"), Some("smart_typography"));
+    assert_eq!(suggest_key("langauge"), None);
+    assert_eq!(suggest_key("colour"), None);
+  }
+
+  #[test]
+  fn reports_unknown_keys_with_position() {
+    let contents = "{\n  \"input\": \"docs\",\n  \"ouptut\": \"out\",\n  \"theme\": \"dark\"\n}";
+    let errors = parse_config(contents, ConfigFormat::Json).unwrap_err();
+
+    assert_eq!(
+      errors.iter().map(ToString::to_string).collect::<Vec<String>>(),
+      [
+        "line 3, column 3: unknown key 'ouptut', did you mean 'output'?",
+        "line 4, column 3: unknown key 'theme'",
+      ]
+    );
+  }
+
+  #[test]
+  fn finds_unknown_keys_inside_a_line() {
+    let json_errors = parse_config("{\"title\": \"ouptut\", \"ouptut\": \"x\"}", ConfigFormat::Json).unwrap_err();
+    let yaml_errors = parse_config("{input: docs, theme: dark}\n", ConfigFormat::Yaml).unwrap_err();
+
+    assert_eq!((json_errors[0].line, json_errors[0].column), (Some(1), Some(21)));
+    assert_eq!((yaml_errors[0].line, yaml_errors[0].column), (Some(1), Some(15)));
+  }
+
+  #[test]
+  fn reports_type_errors_with_position() {
+    let json_error = &parse_config("{\n  \"reflow\": \"yes\"\n}", ConfigFormat::Json).unwrap_err()[0];
+    let toml_error = &parse_config("input = \"docs\"\nreflow = 3\n", ConfigFormat::Toml).unwrap_err()[0];
+    let yaml_error = &parse_config("input: docs\nreflow: [1]\n", ConfigFormat::Yaml).unwrap_err()[0];
+
+    assert_eq!(json_error.line, Some(2));
+    assert!(json_error
+      .message
+      .starts_with("invalid type: string \"yes\", expected a boolean"));
+    assert_eq!((toml_error.line, toml_error.column), (Some(2), Some(10)));
+    assert_eq!(yaml_error.line, Some(2));
+  }
+
+  #[test]
+  fn reports_syntax_errors_with_message() {
+    let error = &parse_config("{\n  \"input\": \"docs\"\n  \"reflow\": true\n}", ConfigFormat::Json).unwrap_err()[0];
+
+    assert_eq!(error.to_string(), "line 3, column 3: expected `,` or `}`");
+  }
+
+  #[test]
+  fn config_keys_match_config_fields() {
+    let config = Config {
+      input: Some(String::new()),
+      output: Some(String::new()),
+      output_format: Some(OutputFormat::Html),
+      lang: Some(String::new()),
+      permalinks: Some(false),
+      chapters: Some(false),
+      reflow: Some(false),
+      strip_gutenberg: Some(false),
+      smart_typography: Some(false),
+      emoji: Some(false),
+      emoji_in_txt: Some(false),
+      definition_lists: Some(false),
+      abbreviations: Some(false),
+      superscript: Some(false),
+      subscript: Some(false),
+      template: Some(String::new()),
+      shortcodes: Some(String::new()),
+      fail_fast: Some(false),
+      title: Some(String::new()),
+      base_url: Some(String::new()),
+      author: Some(String::new()),
+      description: Some(String::new()),
+      favicon: Some(String::new()),
+      copyright: Some(String::new()),
+      nav: Some(Vec::new()),
+      extra: Some(Map::new()),
+      hooks: Some(Vec::new()),
+    };
+
+    // Every field is written under its key, and each key is read back
+    let values = to_values(&config);
+    let mut keys: Vec<&str> = values.keys().map(String::as_str).collect();
+    let mut config_keys = CONFIG_KEYS.to_vec();
+    keys.sort_unstable();
+    config_keys.sort_unstable();
+    assert_eq!(keys, config_keys);
+    let mut flag_keys: Vec<&str> = values
+      .iter()
+      .filter(|(_, value)| value.is_boolean())
+      .map(|(key, _)| key.as_str())
+      .collect();
+    let mut expected_flag_keys = FLAG_KEYS.to_vec();
+    flag_keys.sort_unstable();
+    expected_flag_keys.sort_unstable();
+    assert_eq!(flag_keys, expected_flag_keys);
+    let json = serde_json::to_string(&values).unwrap();
+    assert!(parse_config(&json, ConfigFormat::Json).is_ok());
+  }
+
+  #[test]
+  fn names_the_source_of_invalid_values() {
+    let mut layered_config = LayeredConfig::with_defaults();
+    layered_config.merge_env_vars([("ROST_GEN_REFLOW".to_string(), "maybe".to_string())].into_iter());
+
+    let message = layered_config.config().unwrap_err();
+    assert!(message.starts_with("reflow (environment variable ROST_GEN_REFLOW): invalid type"));
+  }
+}
@@ -0,0 +1,1619 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crate::{
+  chapters, extensions, front_matter, gutenberg, headings, math, reflow, shortcodes, ConversionOptions, Format,
+};
+
+// Markdown line replaced by the page's table of contents
+const TOC_MARKER: &str = "[[toc]]";
+// Starts and ends a `:::kind` admonition
+const CONTAINER_FENCE: &str = ":::";
+// Starts and ends math shown on a line of its own
+const MATH_FENCE: &str = "$$";
+// Starts and ends a fenced code block
+const CODE_FENCE: &str = "```";
+
+/// Where a node came from in the source text, as a byte range and the line it
+/// starts on
+/// Nodes added by the parser, like a table of contents requested in front
+/// matter, have an empty range where they were added
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Span {
+  pub start: usize,
+  pub end: usize,
+  /// Counted from 1
+  pub line: usize,
+}
+
+/// A parsed page, which can be inspected or changed before it's rendered
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Document {
+  pub format: Format,
+  pub metadata: Metadata,
+  /// The page's main heading, shown above the rest of the page and left out
+  /// of the table of contents
+  pub title: Option<Heading>,
+  pub blocks: Vec<Block>,
+  /// Whether the last line of the page ends with a line break, which html
+  /// pages keep after it
+  pub ends_with_newline: bool,
+  /// Problems found in the source that didn't stop it being parsed
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub warnings: Vec<Warning>,
+}
+
+/// A problem in the source, like a shortcode without a template
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Warning {
+  pub line: usize,
+  pub message: String,
+  /// The text of the line, without its line ending
+  pub source: String,
+}
+
+/// Information about a page from its front matter, Project Gutenberg header or
+/// title line
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Metadata {
+  pub title: Option<String>,
+  pub author: Option<String>,
+  /// Whether quotes and dashes are replaced with typographic characters
+  pub smart_typography: bool,
+  /// Whether `:name:` shortcodes are replaced with emoji
+  pub emoji: bool,
+  /// Every `key: value` pair in the front matter, with lowercase keys
+  pub front_matter: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Block {
+  Heading(Heading),
+  Paragraph {
+    content: Vec<Inline>,
+    span: Span,
+  },
+  /// An empty line, which ends the paragraph before it
+  BlankLine {
+    span: Span,
+  },
+  /// A `---` line in markdown
+  ThematicBreak {
+    span: Span,
+  },
+  /// Where the table of contents listing the page's headings goes
+  TableOfContents {
+    span: Span,
+  },
+  /// A `{{< name >}}` or `{% name %}...{% endname %}` shortcode in markdown
+  Shortcode(Shortcode),
+  Admonition(Admonition),
+  /// `$$` math on lines of its own, in TeX
+  Math {
+    tex: String,
+    span: Span,
+  },
+  /// Terms each followed by `: definition` lines in markdown
+  DefinitionList {
+    items: Vec<DefinitionItem>,
+    span: Span,
+  },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Heading {
+  /// From 1 to 6
+  pub level: usize,
+  /// Unique within the page, so the heading can be linked to
+  pub id: String,
+  pub content: Vec<Inline>,
+  pub span: Span,
+}
+
+/// A term in a definition list and its definitions
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DefinitionItem {
+  pub term: Vec<Inline>,
+  pub definitions: Vec<Vec<Inline>>,
+  pub span: Span,
+}
+
+/// A note, tip or warning box, from a `> [!KIND]` alert or a `:::kind`
+/// container in markdown
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Admonition {
+  /// Lowercase e.g. `note` or `warning`, used as its class
+  pub kind: String,
+  pub title: Option<String>,
+  pub body: Vec<Block>,
+  pub span: Span,
+}
+
+/// A shortcode, written to the page using the template with its name
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Shortcode {
+  pub name: String,
+  /// Arguments without a name, in the order they were given
+  pub args: Vec<String>,
+  /// `key=value` arguments
+  pub named_args: BTreeMap<String, String>,
+  /// The blocks between the opening and closing tags, if the shortcode has
+  /// them
+  pub body: Option<Vec<Block>>,
+  pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Inline {
+  /// Text, which can contain html that is written to the page as is
+  Text(String),
+  Link {
+    text: String,
+    url: String,
+    span: Span,
+  },
+  /// The end of a line inside a paragraph
+  SoftBreak,
+  /// A line break kept when reflowing a paragraph
+  LineBreak,
+  /// `$...$` math in TeX, or `$$...$$` math shown on a line of its own
+  Math {
+    tex: String,
+    display: bool,
+    span: Span,
+  },
+  /// A term defined with a `*[term]: title` line, wherever it appears
+  Abbreviation {
+    text: String,
+    title: String,
+  },
+  /// `^text^`
+  Superscript(String),
+  /// `~text~`
+  Subscript(String),
+}
+
+impl Document {
+  /// Every heading except the title, in the order they appear
+  pub fn headings(&self) -> impl Iterator<Item = &Heading> {
+    self.blocks.iter().filter_map(|block| match block {
+      Block::Heading(heading) => Some(heading),
+      _ => None,
+    })
+  }
+
+  /// The content of the title, headings and paragraphs, including those in
+  /// shortcodes and admonitions, for changing text and links across the page
+  pub fn inlines_mut(&mut self) -> impl Iterator<Item = &mut Vec<Inline>> {
+    let mut contents = Vec::new();
+    if let Some(title) = &mut self.title {
+      contents.push(&mut title.content);
+    }
+    collect_inlines_mut(&mut self.blocks, &mut contents);
+    contents.into_iter()
+  }
+}
+
+fn collect_inlines_mut<'a>(blocks: &'a mut [Block], contents: &mut Vec<&'a mut Vec<Inline>>) {
+  for block in blocks {
+    match block {
+      Block::Heading(heading) => contents.push(&mut heading.content),
+      Block::Paragraph { content, .. } => contents.push(content),
+      Block::Shortcode(Shortcode { body: Some(body), .. }) | Block::Admonition(Admonition { body, .. }) => {
+        collect_inlines_mut(body, contents)
+      }
+      Block::DefinitionList { items, .. } => {
+        for item in items {
+          contents.push(&mut item.term);
+          contents.extend(&mut item.definitions);
+        }
+      }
+      _ => {}
+    }
+  }
+}
+
+impl fmt::Display for Warning {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    write!(formatter, "line {}: {}\n  {}", self.line, self.message, self.source)
+  }
+}
+
+impl Block {
+  pub fn span(&self) -> Span {
+    match self {
+      Block::Heading(heading) => heading.span,
+      Block::Shortcode(shortcode) => shortcode.span,
+      Block::Admonition(admonition) => admonition.span,
+      Block::Math { span, .. } | Block::DefinitionList { span, .. } => *span,
+      Block::Paragraph { span, .. } | Block::BlankLine { span } | Block::ThematicBreak { span } => *span,
+      Block::TableOfContents { span } => *span,
+    }
+  }
+}
+
+impl Heading {
+  /// The heading without any markup, as listed in the table of contents
+  pub fn text(&self) -> String { inline_text(&self.content) }
+}
+
+/// The text of inline nodes without any markup
+pub fn inline_text(inlines: &[Inline]) -> String {
+  let mut text = String::new();
+  for inline in inlines {
+    match inline {
+      Inline::Text(inline_text)
+      | Inline::Link { text: inline_text, .. }
+      | Inline::Math { tex: inline_text, .. }
+      | Inline::Abbreviation { text: inline_text, .. }
+      | Inline::Superscript(inline_text)
+      | Inline::Subscript(inline_text) => text.push_str(inline_text),
+      Inline::SoftBreak | Inline::LineBreak => text.push(' '),
+    }
+  }
+  text
+}
+
+// A line of the source and where it starts
+struct Line<'a> {
+  text: &'a str,
+  start: usize,
+  number: usize,
+  // Whether the line is part of a markdown code block, where math isn't
+  // looked for
+  code: bool,
+}
+
+impl Line<'_> {
+  fn span(&self) -> Span {
+    Span {
+      start: self.start,
+      end: self.start + self.text.len(),
+      line: self.number,
+    }
+  }
+
+  // The line without its line ending
+  fn content(&self) -> &str { self.text.trim_end_matches(['\n', '\r']) }
+
+  fn is_blank(&self) -> bool { self.text.trim().is_empty() }
+
+  // Only a line with nothing but its line ending separates paragraphs, and a
+  // line of spaces is kept in the paragraph
+  fn is_empty(&self) -> bool { matches!(self.text, "\n" | "\r\n") }
+
+  fn warning(&self, message: String) -> Warning {
+    Warning {
+      line: self.number,
+      message,
+      source: self.content().to_string(),
+    }
+  }
+
+  // The line without the `>` quoting it
+  fn unquoted(&self) -> Self {
+    let text = self.text.strip_prefix('>').unwrap_or(self.text);
+    let text = text.strip_prefix(' ').unwrap_or(text);
+    Line {
+      text,
+      start: self.start + (self.text.len() - text.len()),
+      number: self.number,
+      code: self.code,
+    }
+  }
+}
+
+/// Parse text or markdown into a document, using the options that affect how
+/// the source is read e.g. chapters and reflow
+pub fn parse(source: &str, format: Format, options: &ConversionOptions) -> Document {
+  // Front matter is only supported at the start of markdown files
+  let (front_matter, front_matter_length) = if format == Format::Markdown {
+    front_matter::parse_front_matter(source)
+  } else {
+    (front_matter::FrontMatter::default(), 0)
+  };
+  let mut body = &source[front_matter_length..];
+  let mut metadata = Metadata {
+    title: front_matter.get("title").map(str::to_string),
+    author: front_matter.get("author").map(str::to_string),
+    // Front matter can turn smart typography on or off for a single page
+    smart_typography: front_matter
+      .flag("smart_typography")
+      .unwrap_or(options.smart_typography),
+    emoji: front_matter.flag("emoji").unwrap_or(match format {
+      Format::Markdown => options.emoji,
+      Format::Text => options.emoji && options.emoji_in_txt,
+    }),
+    front_matter: front_matter.values().clone(),
+  };
+
+  // The body is part of the source, so spans are found from where it starts
+  let mut body_start = front_matter_length;
+
+  // Remove the Project Gutenberg header and licence, keeping the metadata from
+  // the header
+  if format == Format::Text && options.strip_gutenberg {
+    if let Some(gutenberg_text) = gutenberg::strip_gutenberg(body) {
+      body = gutenberg_text.body;
+      body_start += gutenberg_text.body_start;
+      metadata.title = gutenberg_text.title.or(metadata.title);
+      metadata.author = gutenberg_text.author.or(metadata.author);
+    }
+  }
+  let mut heading_ids = headings::HeadingIds::default();
+
+  // Try to find title
+  // Title is first line followed by two blank lines
+  let mut title_line = String::new();
+  let mut title_length = parse_title(body, &mut title_line);
+  // A title from the front matter or Project Gutenberg header leaves the first
+  // line in the page
+  if metadata.title.is_some() {
+    title_length = 0;
+  }
+  let title = match metadata.title.clone() {
+    Some(metadata_title) => Some((
+      metadata_title,
+      Span {
+        start: 0,
+        end: body_start,
+        line: 1,
+      },
+    )),
+    None if title_length > 0 => Some((
+      title_line.trim_end().to_string(),
+      Span {
+        start: body_start,
+        end: body_start + title_line.len(),
+        line: line_number(source, body_start),
+      },
+    )),
+    None => None,
+  };
+  metadata.title = title.as_ref().map(|(text, _)| text.clone());
+  let title = title.map(|(text, span)| Heading {
+    level: 1,
+    id: heading_ids.unique_id(&text),
+    content: vec![Inline::Text(text)],
+    span,
+  });
+
+  // Skip the title to prevent printing it twice
+  let lines_start = body_start + title_length;
+  let first_line_number = line_number(source, lines_start);
+  let mut start = lines_start;
+  let mut lines = Vec::new();
+  for (i, text) in source[lines_start..(body_start + body.len())]
+    .split_inclusive('\n')
+    .enumerate()
+  {
+    lines.push(Line {
+      text,
+      start,
+      number: first_line_number + i,
+      code: false,
+    });
+    start += text.len();
+  }
+  if format == Format::Markdown {
+    mark_code_lines(&mut lines);
+  }
+
+  // A block at the start with some `key: value` lines and some other lines
+  // is kept in the page, with a warning for each line that couldn't be read
+  let mut warnings: Vec<Warning> = front_matter
+    .unparsed_lines
+    .iter()
+    .map(|(line, text)| Warning {
+      line: *line,
+      message: "not a 'key: value' pair, so the block at the start isn't read as front matter".to_string(),
+      source: text.clone(),
+    })
+    .collect();
+  let mut blocks = match format {
+    Format::Text => parse_text_blocks(&lines, options, &mut heading_ids),
+    Format::Markdown => parse_markdown_blocks(&lines, options, &mut heading_ids, &mut warnings),
+  };
+  check_math(&blocks, source, &mut warnings);
+  warnings.sort_by_key(|warning| warning.line);
+
+  // The table of contents goes where the marker is, or at the top of the page
+  // if requested in front matter or chapters were found in a text file
+  let toc_requested =
+    front_matter.flag("toc") == Some(true) || (format == Format::Text && blocks.iter().any(is_heading));
+  if toc_requested
+    && !blocks
+      .iter()
+      .any(|block| matches!(block, Block::TableOfContents { .. }))
+  {
+    blocks.insert(
+      0,
+      Block::TableOfContents {
+        span: Span {
+          start: lines_start,
+          end: lines_start,
+          line: first_line_number,
+        },
+      },
+    );
+  }
+
+  let mut document = Document {
+    format,
+    metadata,
+    title,
+    blocks,
+    // Reflowed paragraphs are always written with a line break after them
+    ends_with_newline: lines.last().is_none_or(|line| line.text.ends_with('\n'))
+      || (format == Format::Text && options.reflow),
+    warnings,
+  };
+  if format == Format::Markdown {
+    apply_inline_extensions(&mut document, &lines, options);
+  }
+  document
+}
+
+// Superscript, subscript and abbreviations are found in the text of the
+// parsed page, so they work in headings and definitions as well as paragraphs
+fn apply_inline_extensions(document: &mut Document, lines: &[Line], options: &ConversionOptions) {
+  let mut abbreviations: Vec<(String, String)> = Vec::new();
+  if options.abbreviations {
+    // Later definitions of an abbreviation replace earlier ones
+    for (abbreviation, title) in lines
+      .iter()
+      .filter_map(|line| extensions::parse_abbreviation(line.text))
+    {
+      abbreviations.retain(|(existing, _)| *existing != abbreviation);
+      abbreviations.push((abbreviation, title));
+    }
+    abbreviations.sort_by_key(|(abbreviation, _)| std::cmp::Reverse(abbreviation.len()));
+  }
+  if !options.superscript && !options.subscript && abbreviations.is_empty() {
+    return;
+  }
+
+  for content in document.inlines_mut() {
+    *content = content
+      .drain(..)
+      .flat_map(|inline| match inline {
+        Inline::Text(text) => extensions::split_scripts(&text, options.superscript, options.subscript),
+        inline => vec![inline],
+      })
+      .flat_map(|inline| match inline {
+        Inline::Text(text) if !abbreviations.is_empty() => extensions::split_abbreviations(&text, &abbreviations),
+        inline => vec![inline],
+      })
+      .collect();
+  }
+}
+
+// Blank lines separate paragraphs, and chapter headings sit on their own line
+// between blank lines
+fn parse_text_blocks(
+  lines: &[Line],
+  options: &ConversionOptions,
+  heading_ids: &mut headings::HeadingIds,
+) -> Vec<Block> {
+  let line_texts: Vec<&str> = lines.iter().map(|line| line.text).collect();
+  let wrap_width = reflow::wrap_width(&line_texts);
+  let mut blocks = Vec::new();
+  let mut paragraph_lines: Vec<&Line> = Vec::new();
+
+  for (i, line) in lines.iter().enumerate() {
+    let standalone_line = (i == 0 || lines[i - 1].is_blank()) && lines.get(i + 1).is_none_or(Line::is_blank);
+
+    if line.is_empty() {
+      push_text_paragraph(&mut blocks, &mut paragraph_lines, options.reflow, wrap_width);
+      blocks.push(Block::BlankLine { span: line.span() });
+    } else if options.chapters && standalone_line && chapters::is_chapter_heading(line.text) {
+      push_text_paragraph(&mut blocks, &mut paragraph_lines, options.reflow, wrap_width);
+      let heading = line.text.trim();
+      blocks.push(Block::Heading(Heading {
+        level: 2,
+        id: heading_ids.unique_id(heading),
+        content: vec![Inline::Text(heading.to_string())],
+        span: line.span(),
+      }));
+    } else {
+      paragraph_lines.push(line);
+    }
+  }
+
+  push_text_paragraph(&mut blocks, &mut paragraph_lines, options.reflow, wrap_width);
+  blocks
+}
+
+fn parse_markdown_blocks(
+  lines: &[Line],
+  options: &ConversionOptions,
+  heading_ids: &mut headings::HeadingIds,
+  warnings: &mut Vec<Warning>,
+) -> Vec<Block> {
+  let mut blocks = Vec::new();
+  let mut paragraph_lines: Vec<&Line> = Vec::new();
+  let mut i = 0;
+
+  while i < lines.len() {
+    let line = &lines[i];
+    i += 1;
+
+    if let Some(tag) = shortcodes::parse_tag(line.text) {
+      let warning = |message: String| line.warning(message);
+      let end = if tag.paired {
+        closing_tag_index(lines, i, &tag.name)
+      } else {
+        Some(i - 1)
+      };
+      match end {
+        // Unclosed shortcodes are kept as text
+        None => warnings.push(warning(format!(
+          "shortcode '{}' is not closed with {{% end{} %}}",
+          tag.name, tag.name
+        ))),
+        Some(end) => {
+          // Shortcodes without a template are written as just their body
+          if !options.shortcodes.contains_key(&tag.name) {
+            warnings.push(warning(format!("no template found for shortcode '{}'", tag.name)));
+          }
+          push_markdown_paragraph(&mut blocks, &mut paragraph_lines);
+          let body = tag
+            .paired
+            .then(|| parse_markdown_blocks(&lines[i..end], options, heading_ids, warnings));
+          blocks.push(Block::Shortcode(Shortcode {
+            name: tag.name,
+            args: tag.args,
+            named_args: tag.named_args,
+            body,
+            span: Span {
+              start: line.start,
+              end: lines[end].span().end,
+              line: line.number,
+            },
+          }));
+          i = end + 1;
+          continue;
+        }
+      }
+    }
+
+    // Math on a line of its own, or between two `$$` lines
+    if let Some(tex) = line.content().trim().strip_prefix(MATH_FENCE).filter(|_| !line.code) {
+      let end = match tex.strip_suffix(MATH_FENCE) {
+        Some(tex) if !tex.trim().is_empty() && !tex.contains(MATH_FENCE) => Some(i - 1),
+        _ if tex.is_empty() => lines[i..]
+          .iter()
+          .position(|line| line.text.trim() == MATH_FENCE)
+          .map(|length| i + length),
+        _ => None,
+      };
+      match end {
+        None if tex.is_empty() => warnings.push(line.warning("math is not closed with a $$ line".to_string())),
+        None => {}
+        Some(end) => {
+          let tex = if end + 1 == i {
+            tex.trim_end_matches(MATH_FENCE).trim().to_string()
+          } else {
+            lines[i..end].iter().map(Line::content).collect::<Vec<_>>().join("\n")
+          };
+          push_markdown_paragraph(&mut blocks, &mut paragraph_lines);
+          blocks.push(Block::Math {
+            tex,
+            span: Span {
+              start: line.start,
+              end: lines[end].span().end,
+              line: line.number,
+            },
+          });
+          i = end + 1;
+          continue;
+        }
+      }
+    }
+
+    // Alerts go on until the first line that isn't quoted
+    if let Some((kind, title)) = parse_alert_marker(line.text) {
+      let end = lines[i..]
+        .iter()
+        .position(|line| !line.text.starts_with('>'))
+        .map_or(lines.len(), |length| i + length);
+      let body_lines: Vec<Line> = lines[i..end].iter().map(Line::unquoted).collect();
+      push_markdown_paragraph(&mut blocks, &mut paragraph_lines);
+      blocks.push(Block::Admonition(Admonition {
+        kind,
+        title,
+        body: parse_markdown_blocks(&body_lines, options, heading_ids, warnings),
+        span: Span {
+          start: line.start,
+          end: lines[end - 1].span().end,
+          line: line.number,
+        },
+      }));
+      i = end;
+      continue;
+    }
+
+    // Unclosed containers are kept as text
+    if let Some((kind, title)) = parse_container_marker(line.text) {
+      match closing_container_index(lines, i) {
+        None => warnings.push(line.warning(format!("'{}' is not closed with a ::: line", line.content().trim()))),
+        Some(end) => {
+          push_markdown_paragraph(&mut blocks, &mut paragraph_lines);
+          blocks.push(Block::Admonition(Admonition {
+            kind,
+            title,
+            body: parse_markdown_blocks(&lines[i..end], options, heading_ids, warnings),
+            span: Span {
+              start: line.start,
+              end: lines[end].span().end,
+              line: line.number,
+            },
+          }));
+          i = end + 1;
+          continue;
+        }
+      }
+    }
+
+    // Abbreviations are collected from the whole page, and their definitions
+    // aren't shown
+    if options.abbreviations && extensions::parse_abbreviation(line.text).is_some() {
+      continue;
+    }
+
+    // A definition list starts with a line of its own followed by a
+    // definition
+    if options.definition_lists
+      && paragraph_lines.is_empty()
+      && is_term(lines, i - 1)
+      && headings::parse_heading(line.text).is_none()
+    {
+      push_markdown_paragraph(&mut blocks, &mut paragraph_lines);
+      let (items, end) = parse_definition_items(lines, i - 1);
+      blocks.push(Block::DefinitionList {
+        items,
+        span: Span {
+          start: line.start,
+          end: lines[end - 1].span().end,
+          line: line.number,
+        },
+      });
+      i = end;
+      continue;
+    }
+
+    if line.is_empty() {
+      push_markdown_paragraph(&mut blocks, &mut paragraph_lines);
+      blocks.push(Block::BlankLine { span: line.span() });
+    } else if let Some((level, heading)) = headings::parse_heading(line.text) {
+      push_markdown_paragraph(&mut blocks, &mut paragraph_lines);
+      let heading = heading.trim_end();
+      blocks.push(Block::Heading(Heading {
+        level,
+        id: heading_ids.unique_id(heading),
+        content: vec![Inline::Text(heading.to_string())],
+        span: line.span(),
+      }));
+    } else if matches!(line.text, "---\n" | "---\r\n") {
+      push_markdown_paragraph(&mut blocks, &mut paragraph_lines);
+      blocks.push(Block::ThematicBreak { span: line.span() });
+    } else if line.text.trim() == TOC_MARKER {
+      push_markdown_paragraph(&mut blocks, &mut paragraph_lines);
+      blocks.push(Block::TableOfContents { span: line.span() });
+    } else {
+      paragraph_lines.push(line);
+    }
+  }
+
+  push_markdown_paragraph(&mut blocks, &mut paragraph_lines);
+  blocks
+}
+
+// Marks the lines of ``` fenced code blocks, fences included, and of indented
+// code blocks, which start after a blank line
+fn mark_code_lines(lines: &mut [Line]) {
+  let mut in_fence = false;
+  let mut in_indented_block = false;
+  for i in 0..lines.len() {
+    let after_blank_line = i == 0 || lines[i - 1].is_blank();
+    let line = &mut lines[i];
+    let fence = line.text.trim_start().starts_with(CODE_FENCE);
+    if fence {
+      in_fence = !in_fence;
+    }
+    let indented = line.text.starts_with("    ") || line.text.starts_with('\t');
+    in_indented_block = !in_fence && !fence && !line.is_blank() && indented && (in_indented_block || after_blank_line);
+    line.code = fence || in_fence || in_indented_block;
+  }
+}
+
+// Whether the line is a term, followed by its first definition
+fn is_term(lines: &[Line], index: usize) -> bool {
+  let line = &lines[index];
+  !line.is_blank()
+    && extensions::parse_definition(line.text).is_none()
+    && lines
+      .get(index + 1)
+      .is_some_and(|next_line| extensions::parse_definition(next_line.text).is_some())
+}
+
+// Terms and their definitions from the term at the start, where terms can be
+// separated by blank lines
+// returns the items and the index of the line after the last definition
+fn parse_definition_items(lines: &[Line], start: usize) -> (Vec<DefinitionItem>, usize) {
+  let mut items = Vec::new();
+  let mut term_index = start;
+
+  loop {
+    let term_line = &lines[term_index];
+    let mut end = term_index + 1;
+    let mut definitions = Vec::new();
+    while let Some((definition_start, definition)) = lines
+      .get(end)
+      .and_then(|line| extensions::parse_definition(line.content()))
+    {
+      let line = &lines[end];
+      definitions.push(parse_inlines(definition, line.start + definition_start, line.number));
+      end += 1;
+    }
+    items.push(DefinitionItem {
+      term: parse_inlines(term_line.content().trim(), term_line.start, term_line.number),
+      definitions,
+      span: Span {
+        start: term_line.start,
+        end: lines[end - 1].span().end,
+        line: term_line.number,
+      },
+    });
+
+    let next_term_index = lines[end..]
+      .iter()
+      .position(|line| !line.is_blank())
+      .map(|length| end + length);
+    match next_term_index {
+      Some(index) if is_term(lines, index) && headings::parse_heading(lines[index].text).is_none() => {
+        term_index = index
+      }
+      _ => return (items, end),
+    }
+  }
+}
+
+// Index of the tag closing a paired shortcode opened before the first line,
+// skipping shortcodes with the same name nested inside it
+fn closing_tag_index(lines: &[Line], first_line: usize, name: &str) -> Option<usize> {
+  let closing_name = format!("end{name}");
+  let mut depth = 0;
+  for (i, line) in lines.iter().enumerate().skip(first_line) {
+    match shortcodes::parse_tag(line.text) {
+      Some(tag) if tag.paired && tag.name == name => depth += 1,
+      Some(tag) if tag.paired && tag.name == closing_name && depth == 0 => return Some(i),
+      Some(tag) if tag.paired && tag.name == closing_name => depth -= 1,
+      _ => {}
+    }
+  }
+  None
+}
+
+// `> [!KIND] title`, which starts a GitHub style alert
+// returns the lowercase kind and the title, if there is one
+fn parse_alert_marker(line: &str) -> Option<(String, Option<String>)> {
+  let marker = line.trim_end().strip_prefix('>')?.trim_start().strip_prefix("[!")?;
+  let (kind, title) = marker.split_once(']')?;
+  admonition_kind(kind).map(|kind| (kind, admonition_title(title)))
+}
+
+// `:::kind title`, which starts a container closed by a `:::` line
+fn parse_container_marker(line: &str) -> Option<(String, Option<String>)> {
+  let marker = line.trim().strip_prefix(CONTAINER_FENCE)?.trim_start();
+  let (kind, title) = marker.split_once(char::is_whitespace).unwrap_or((marker, ""));
+  admonition_kind(kind).map(|kind| (kind, admonition_title(title)))
+}
+
+// Kinds are used as class names, so they can only have letters, numbers, - and
+// _
+fn admonition_kind(kind: &str) -> Option<String> {
+  let valid = !kind.is_empty()
+    && kind
+      .chars()
+      .all(|char| char.is_ascii_alphanumeric() || char == '-' || char == '_');
+  valid.then(|| kind.to_lowercase())
+}
+
+fn admonition_title(title: &str) -> Option<String> {
+  Some(title.trim()).filter(|title| !title.is_empty()).map(str::to_string)
+}
+
+// Index of the `:::` line closing a container opened before the first line,
+// skipping containers nested inside it
+fn closing_container_index(lines: &[Line], first_line: usize) -> Option<usize> {
+  let mut depth = 0;
+  for (i, line) in lines.iter().enumerate().skip(first_line) {
+    if line.text.trim() == CONTAINER_FENCE {
+      if depth == 0 {
+        return Some(i);
+      }
+      depth -= 1;
+    } else if parse_container_marker(line.text).is_some() {
+      depth += 1;
+    }
+  }
+  None
+}
+
+// Lines are kept as they are, or joined into flowing text if reflow is on
+fn push_text_paragraph(blocks: &mut Vec<Block>, paragraph_lines: &mut Vec<&Line>, reflow: bool, wrap_width: usize) {
+  let Some(span) = paragraph_span(paragraph_lines) else {
+    return;
+  };
+
+  let content = if reflow {
+    let line_texts: Vec<&str> = paragraph_lines.iter().map(|line| line.text).collect();
+    let reflowed_lines = reflow::reflow_paragraph(&line_texts, wrap_width);
+    join_lines(
+      reflowed_lines.into_iter().map(|line| vec![Inline::Text(line)]),
+      Inline::LineBreak,
+    )
+  } else {
+    join_lines(
+      paragraph_lines
+        .iter()
+        .map(|line| vec![Inline::Text(line.content().to_string())]),
+      Inline::SoftBreak,
+    )
+  };
+
+  blocks.push(Block::Paragraph { content, span });
+  paragraph_lines.clear();
+}
+
+fn push_markdown_paragraph(blocks: &mut Vec<Block>, paragraph_lines: &mut Vec<&Line>) {
+  let Some(span) = paragraph_span(paragraph_lines) else {
+    return;
+  };
+
+  let content = join_lines(
+    paragraph_lines.iter().map(|line| match line.code {
+      true => parse_links(line.content(), line.start, line.number),
+      false => parse_inlines(line.content(), line.start, line.number),
+    }),
+    Inline::SoftBreak,
+  );
+
+  blocks.push(Block::Paragraph { content, span });
+  paragraph_lines.clear();
+}
+
+// returns None if there are no lines
+fn paragraph_span(paragraph_lines: &[&Line]) -> Option<Span> {
+  let first_line = paragraph_lines.first()?;
+  let last_line = paragraph_lines.last()?;
+  Some(Span {
+    start: first_line.start,
+    end: last_line.span().end,
+    line: first_line.number,
+  })
+}
+
+fn join_lines(lines: impl Iterator<Item = Vec<Inline>>, separator: Inline) -> Vec<Inline> {
+  let mut content = Vec::new();
+  for line in lines {
+    if !content.is_empty() {
+      content.push(separator.clone());
+    }
+    content.extend(line);
+  }
+  content
+}
+
+fn is_heading(block: &Block) -> bool { matches!(block, Block::Heading(_)) }
+
+fn line_number(source: &str, offset: usize) -> usize { source[..offset].matches('\n').count() + 1 }
+
+// returns no of bytes to skip if title is found, otherwise 0
+fn parse_title(contents: &str, title: &mut String) -> usize {
+  let mut lines = contents.split_inclusive('\n');
+  let line1 = lines.next().unwrap_or_default();
+  let line2 = lines.next().unwrap_or_default();
+  let line3 = lines.next().unwrap_or_default();
+
+  // Check if line 1 is not empty and not a blank line, and is followed by two
+  // empty lines
+  if (!line1.is_empty() && line1 != "\n" && line1 != "\r\n")
+    && (line2 == "\n" || line2 == "\r\n")
+    && (line3 == "\n" || line3 == "\r\n")
+  {
+    *title = line1.to_string();
+    line1.len() + line2.len() + line3.len()
+  } else {
+    0
+  }
+}
+
+/// Split a line of markdown into text, `$...$` math and `[text](url)` links
+/// Only the first link between each piece of math is found, and math is kept
+/// as written, so `[` or `\$` in it aren't treated as markdown
+/// Dollar signs in backtick spans aren't math
+/// The start and line number of the line are used for the spans of math and
+/// links
+pub fn parse_inlines(line: &str, line_start: usize, line_number: usize) -> Vec<Inline> {
+  let mut inlines = Vec::new();
+  let mut text_start = 0;
+
+  while let Some(math) = find_math(line, text_start) {
+    inlines.extend(parse_links(
+      &line[text_start..math.start],
+      line_start + text_start,
+      line_number,
+    ));
+    inlines.push(Inline::Math {
+      tex: line[math.tex_start..math.tex_end].to_string(),
+      display: math.display,
+      span: Span {
+        start: line_start + math.start,
+        end: line_start + math.end,
+        line: line_number,
+      },
+    });
+    text_start = math.end;
+  }
+
+  inlines.extend(parse_links(&line[text_start..], line_start + text_start, line_number));
+  for inline in &mut inlines {
+    if let Inline::Text(text) = inline {
+      *text = unescape_dollars(text);
+    }
+  }
+  inlines
+}
+
+// An escaped dollar sign is written without its backslash, apart from in
+// backtick code spans
+fn unescape_dollars(text: &str) -> String {
+  let mut unescaped = String::with_capacity(text.len());
+  let mut rest = text;
+  while let Some(span_start) = rest.find('`') {
+    let span_end = rest[(span_start + 1)..]
+      .find('`')
+      .map_or(span_start + 1, |length| span_start + length + 2);
+    unescaped.push_str(&rest[..span_start].replace("\\$", "$"));
+    unescaped.push_str(&rest[span_start..span_end]);
+    rest = &rest[span_end..];
+  }
+  unescaped.push_str(&rest.replace("\\$", "$"));
+  unescaped
+}
+
+// Where math starts and ends in a line, with and without its dollar signs
+struct MathRange {
+  start: usize,
+  tex_start: usize,
+  tex_end: usize,
+  end: usize,
+  display: bool,
+}
+
+// The first math in a line at or after an index, skipping backtick spans so
+// shell variables like `$HOME` in code aren't read as math
+fn find_math(line: &str, from: usize) -> Option<MathRange> {
+  let mut i = from;
+  while let Some(char) = line[i..].chars().next() {
+    match char {
+      '\\' => i += 1 + line[(i + 1)..].chars().next().map_or(0, char::len_utf8),
+      '`' => i += line[(i + 1)..].find('`').map_or(1, |length| length + 2),
+      '$' => {
+        let delimiter = if line[i..].starts_with(MATH_FENCE) {
+          MATH_FENCE
+        } else {
+          "$"
+        };
+        let tex_start = i + delimiter.len();
+        if let Some(tex_end) = closing_math_delimiter(line, tex_start, delimiter) {
+          return Some(MathRange {
+            start: i,
+            tex_start,
+            tex_end,
+            end: tex_end + delimiter.len(),
+            display: delimiter == MATH_FENCE,
+          });
+        }
+        i = tex_start;
+      }
+      _ => i += char.len_utf8(),
+    }
+  }
+  None
+}
+
+// Math can't start with a space, and `$` can't end it after a space or before
+// a digit, so prices like "$5 and $10" aren't math
+fn closing_math_delimiter(line: &str, tex_start: usize, delimiter: &str) -> Option<usize> {
+  if line[tex_start..].chars().next().is_none_or(char::is_whitespace) {
+    return None;
+  }
+
+  let mut i = tex_start;
+  while let Some(char) = line[i..].chars().next() {
+    if char == '\\' {
+      i += 1 + line[(i + 1)..].chars().next().map_or(0, char::len_utf8);
+      continue;
+    }
+    if i > tex_start && line[i..].starts_with(delimiter) {
+      let closes = delimiter == MATH_FENCE
+        || !(line[..i].ends_with(char::is_whitespace)
+          || line[(i + 1)..].starts_with(|char: char| char.is_ascii_digit()));
+      if closes {
+        return Some(i);
+      }
+    }
+    i += char.len_utf8();
+  }
+  None
+}
+
+// Math that can't be converted is written as its source, with a warning
+fn check_math(blocks: &[Block], source: &str, warnings: &mut Vec<Warning>) {
+  for block in blocks {
+    match block {
+      Block::Math { tex, span } => check_tex(tex, true, span, source, warnings),
+      Block::Paragraph { content, .. } => check_inline_math(content, source, warnings),
+      Block::Shortcode(Shortcode { body: Some(body), .. }) | Block::Admonition(Admonition { body, .. }) => {
+        check_math(body, source, warnings)
+      }
+      Block::DefinitionList { items, .. } => {
+        for item in items {
+          check_inline_math(&item.term, source, warnings);
+          for definition in &item.definitions {
+            check_inline_math(definition, source, warnings);
+          }
+        }
+      }
+      _ => {}
+    }
+  }
+}
+
+fn check_inline_math(content: &[Inline], source: &str, warnings: &mut Vec<Warning>) {
+  for inline in content {
+    if let Inline::Math { tex, display, span } = inline {
+      check_tex(tex, *display, span, source, warnings);
+    }
+  }
+}
+
+fn check_tex(tex: &str, display: bool, span: &Span, source: &str, warnings: &mut Vec<Warning>) {
+  if let Err(error) = math::to_mathml(tex, display) {
+    let line_start = source[..span.start].rfind('\n').map_or(0, |index| index + 1);
+    let line_end = source[span.start..]
+      .find('\n')
+      .map_or(source.len(), |index| span.start + index);
+    warnings.push(Warning {
+      line: span.line,
+      message: format!("could not convert math, {error}"),
+      source: source[line_start..line_end].trim_end_matches('\r').to_string(),
+    });
+  }
+}
+
+// The first `[text](url)` link in text
+fn parse_links(line: &str, line_start: usize, line_number: usize) -> Vec<Inline> {
+  let line_bytes = line.as_bytes();
+  let mut link_start_found = false;
+  let mut link_end_found = false;
+  let mut link_url_start_found = false;
+  let mut link_url_end_found = false;
+  let mut link_start = 0;
+  let mut link_end = 0;
+  let mut link_url_start = 0;
+  let mut link_url_end = 0;
+
+  for (i, &char) in line_bytes.iter().enumerate() {
+    if !link_start_found && char == b'[' {
+      link_start_found = true;
+      link_start = i + 1;
+    }
+
+    if link_start_found && !link_end_found && char == b']' {
+      link_end_found = true;
+      link_end = i;
+    }
+
+    if link_end_found {
+      if !link_url_start_found && char == b'(' {
+        link_url_start_found = true;
+        link_url_start = i + 1;
+      }
+
+      if link_url_start_found && !link_url_end_found && char == b')' {
+        link_url_end_found = true;
+        link_url_end = i;
+      }
+    }
+  }
+
+  if line.is_empty() {
+    return Vec::new();
+  }
+  if !(link_start_found && link_end_found) {
+    return vec![Inline::Text(line.to_string())];
+  }
+  // `[text]` without a `(url)` after it is a link with an empty url
+  let (url, link_markdown_end) = if link_url_end_found {
+    (&line[link_url_start..link_url_end], link_url_end + 1)
+  } else {
+    ("", link_end + 1)
+  };
+
+  let mut inlines = Vec::new();
+  if link_start > 1 {
+    inlines.push(Inline::Text(line[0..(link_start - 1)].to_string()));
+  }
+  inlines.push(Inline::Link {
+    text: line[link_start..link_end].to_string(),
+    url: url.to_string(),
+    span: Span {
+      start: line_start + link_start - 1,
+      end: line_start + link_markdown_end,
+      line: line_number,
+    },
+  });
+  if link_markdown_end < line_bytes.len() {
+    inlines.push(Inline::Text(line[link_markdown_end..].to_string()));
+  }
+  inlines
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::document::*;
+  use crate::render::{HtmlRenderer, RenderContext, Renderer};
+
+  fn process_link_markdown(line: &str) -> String {
+    let options = ConversionOptions::default();
+    let document = parse("", Format::Markdown, &options);
+    let context = RenderContext {
+      document: &document,
+      options: &options,
+      page_name: None,
+    };
+    HtmlRenderer.render_inlines(&parse_inlines(line, 0, 1), &context)
+  }
+
+  #[test]
+  fn parses_title_when_provided() {
+    let mut output_title = String::new();
+    parse_title("Title\n\n\nText\n", &mut output_title);
+    assert_eq!(output_title, "Title\n");
+  }
+
+  #[test]
+  fn returns_title_size_when_found() {
+    let mut output_title = String::new();
+    let bytes_read = parse_title("Title\n\n\nText\n", &mut output_title);
+    assert_eq!(bytes_read, 8);
+  }
+
+  #[test]
+  fn does_not_change_title_arg_when_no_title() {
+    let mut output_title = String::from("Unchanged");
+    parse_title("Text\n\nMore text\n", &mut output_title);
+    assert_eq!(output_title, "Unchanged");
+  }
+
+  #[test]
+  fn returns_zero_when_no_title() {
+    let mut output_title = String::new();
+    let bytes_read = parse_title("Text\n\nMore text\n", &mut output_title);
+    assert_eq!(bytes_read, 0);
+  }
+
+  #[test]
+  fn processes_one_markdown_link() {
+    let input_line = String::from("[This is text for a link](www.example.com)");
+    let expected_output = "<a href=\"www.example.com\">This is text for a link</a>";
+    assert_eq!(process_link_markdown(&input_line), expected_output);
+  }
+
+  #[test]
+  fn retains_text_before_link() {
+    let input_line = String::from("Lorem Ipsum[This is text for a link](www.example.com)");
+    let expected_output = "Lorem Ipsum<a href=\"www.example.com\">This is text for a link</a>";
+    assert_eq!(process_link_markdown(&input_line), expected_output);
+  }
+
+  #[test]
+  fn retains_text_after_link() {
+    let input_line = String::from("[This is text for a link](www.example.com)Lorem Ipsum");
+    let expected_output = "<a href=\"www.example.com\">This is text for a link</a>Lorem Ipsum";
+    assert_eq!(process_link_markdown(&input_line), expected_output);
+  }
+
+  #[test]
+  fn retains_text_around_link() {
+    let input_line = String::from("Lorem Ipsum[This is text for a link](www.example.com)Dolor Sit");
+    let expected_output = "Lorem Ipsum<a href=\"www.example.com\">This is text for a link</a>Dolor Sit";
+    assert_eq!(process_link_markdown(&input_line), expected_output);
+  }
+
+  #[test]
+  fn does_not_process_invalid_link_markdown() {
+    let input_line = String::from("[Invalid markdown[(www.example.com)");
+    let expected_output = "[Invalid markdown[(www.example.com)";
+    assert_eq!(process_link_markdown(&input_line), expected_output);
+  }
+
+  #[test]
+  fn image_link_test() {
+    let input_line = String::from("[First][Second](www.example.com)");
+    let expected_output = "<a href=\"www.example.com\">First</a>";
+    assert_eq!(process_link_markdown(&input_line), expected_output);
+  }
+
+  #[test]
+  fn processes_link_markdown_without_url() {
+    assert_eq!(
+      process_link_markdown("See [Note] below"),
+      "See <a href=\"\">Note</a> below"
+    );
+  }
+
+  #[test]
+  fn process_link_markdown_returns_empty_string_arg() {
+    assert_eq!(process_link_markdown(""), "");
+  }
+
+  #[test]
+  fn parses_markdown_into_blocks_with_spans() {
+    let source = "---\ntitle: Notes\n---\n# Intro\nSee [docs](docs.html)\nand more\n\n---\n";
+    let document = parse(source, Format::Markdown, &ConversionOptions::default());
+
+    assert_eq!(document.metadata.title.as_deref(), Some("Notes"));
+    assert_eq!(document.title.as_ref().map(Heading::text).as_deref(), Some("Notes"));
+    assert_eq!(document.blocks.len(), 4);
+
+    let heading = document.headings().next().unwrap();
+    assert_eq!(
+      (heading.level, heading.id.as_str(), heading.text()),
+      (1, "intro", "Intro".to_string())
+    );
+    assert_eq!(&source[heading.span.start..heading.span.end], "# Intro\n");
+    assert_eq!(heading.span.line, 4);
+
+    match &document.blocks[1] {
+      Block::Paragraph { content, span } => {
+        assert_eq!(span.line, 5);
+        let link_start = source.find("[docs]").unwrap();
+        assert_eq!(
+          content[1],
+          Inline::Link {
+            text: "docs".to_string(),
+            url: "docs.html".to_string(),
+            span: Span {
+              start: link_start,
+              end: link_start + "[docs](docs.html)".len(),
+              line: 5,
+            },
+          }
+        );
+        assert_eq!(content[2], Inline::SoftBreak);
+      }
+      block => panic!("Expected a paragraph, found {block:?}"),
+    }
+    assert!(matches!(document.blocks[2], Block::BlankLine { span } if span.line == 7));
+    assert_eq!(document.blocks[3].span().line, 8);
+  }
+
+  #[test]
+  fn parses_shortcodes_with_bodies() {
+    let source = "{% note %}\nOuter\n\n{% note %}\nInner\n{% endnote %}\n{% endnote %}\n{{< youtube abc >}}\n";
+    let options = ConversionOptions {
+      shortcodes: HashMap::from([("note".to_string(), "<aside>{{body}}</aside>".to_string())]),
+      ..Default::default()
+    };
+    let document = parse(source, Format::Markdown, &options);
+
+    assert_eq!(document.blocks.len(), 2);
+    match &document.blocks[0] {
+      Block::Shortcode(shortcode) => {
+        let body = shortcode.body.as_ref().unwrap();
+        assert_eq!(body.len(), 3);
+        assert!(matches!(&body[2], Block::Shortcode(inner) if inner.span.line == 4));
+        assert_eq!(
+          &source[shortcode.span.start..shortcode.span.end],
+          &source[..source.find("{{<").unwrap()]
+        );
+      }
+      block => panic!("Expected a shortcode, found {block:?}"),
+    }
+    assert!(
+      matches!(&document.blocks[1], Block::Shortcode(youtube) if youtube.args == ["abc"] && youtube.body.is_none())
+    );
+    assert_eq!(
+      document.warnings,
+      [Warning {
+        line: 8,
+        message: "no template found for shortcode 'youtube'".to_string(),
+        source: "{{< youtube abc >}}".to_string(),
+      }]
+    );
+  }
+
+  #[test]
+  fn keeps_unclosed_shortcodes_as_text() {
+    let document = parse("{% note %}\nText\n", Format::Markdown, &ConversionOptions::default());
+
+    let text = Inline::Text("{% note %}".to_string());
+    assert!(matches!(&document.blocks[0], Block::Paragraph { content, .. } if content[0] == text));
+    assert_eq!(
+      document.warnings[0].to_string(),
+      "line 1: shortcode 'note' is not closed with {% endnote %}\n  {% note %}"
+    );
+  }
+
+  #[test]
+  fn parses_alerts_and_containers_into_admonitions() {
+    let source =
+      "> [!WARNING] Careful\n> Read [this](a.html)\n>\n> twice\nAfter\n\n:::tip\n:::note Inner\nNested\n:::\n:::\n";
+    let document = parse(source, Format::Markdown, &ConversionOptions::default());
+
+    assert_eq!(document.blocks.len(), 4);
+    match &document.blocks[0] {
+      Block::Admonition(admonition) => {
+        assert_eq!(admonition.kind, "warning");
+        assert_eq!(admonition.title.as_deref(), Some("Careful"));
+        assert_eq!(admonition.body.len(), 3);
+        assert_eq!(
+          &source[admonition.span.start..admonition.span.end],
+          "> [!WARNING] Careful\n> Read [this](a.html)\n>\n> twice\n"
+        );
+        match &admonition.body[0] {
+          Block::Paragraph { content, span } => {
+            assert!(matches!(&content[1], Inline::Link { span, .. } if span.start == source.find("[this]").unwrap()));
+            assert_eq!(span.line, 2);
+          }
+          block => panic!("Expected a paragraph, found {block:?}"),
+        }
+      }
+      block => panic!("Expected an admonition, found {block:?}"),
+    }
+    assert_eq!(document.blocks[1].span().line, 5);
+    match &document.blocks[3] {
+      Block::Admonition(admonition) => {
+        assert_eq!((admonition.kind.as_str(), admonition.title.as_ref()), ("tip", None));
+        assert!(matches!(&admonition.body[0], Block::Admonition(inner) if inner.title.as_deref() == Some("Inner")));
+      }
+      block => panic!("Expected an admonition, found {block:?}"),
+    }
+    assert!(document.warnings.is_empty());
+  }
+
+  #[test]
+  fn keeps_unclosed_containers_and_quotes_as_text() {
+    let document = parse(
+      ":::warning\nText\n\n> [!not a kind]\n> Quote\n",
+      Format::Markdown,
+      &ConversionOptions::default(),
+    );
+
+    assert_eq!(document.blocks.len(), 3);
+    assert!(matches!(&document.blocks[0], Block::Paragraph { .. }));
+    assert_eq!(
+      document.warnings[0].to_string(),
+      "line 1: ':::warning' is not closed with a ::: line\n  :::warning"
+    );
+  }
+
+  #[test]
+  fn keeps_math_apart_from_links_and_prices() {
+    let inlines = parse_inlines("Let $[a](b)$ cost \\$5, not $5 or $10", 10, 3);
+
+    assert_eq!(
+      inlines,
+      [
+        Inline::Text("Let ".to_string()),
+        Inline::Math {
+          tex: "[a](b)".to_string(),
+          display: false,
+          span: Span {
+            start: 14,
+            end: 22,
+            line: 3,
+          },
+        },
+        Inline::Text(" cost $5, not $5 or $10".to_string()),
+      ]
+    );
+    assert_eq!(
+      parse_inlines("Run `export PATH=$HOME/bin:$PATH` before $x$", 0, 1)[..2],
+      [
+        Inline::Text("Run `export PATH=$HOME/bin:$PATH` before ".to_string()),
+        Inline::Math {
+          tex: "x".to_string(),
+          display: false,
+          span: Span {
+            start: 41,
+            end: 44,
+            line: 1,
+          },
+        },
+      ]
+    );
+    assert!(matches!(
+      &parse_inlines("$$x^2$$ [y](z)", 0, 1)[..],
+      [Inline::Math { display: true, .. }, Inline::Text(_), Inline::Link { .. }]
+    ));
+  }
+
+  #[test]
+  fn leaves_math_in_code_blocks_alone() {
+    let source = "```\n$x$ or $$y$$\n$$\n```\n\n    let z = $z$;\n\nSee $w$\n";
+    let mut document = parse(source, Format::Markdown, &ConversionOptions::default());
+
+    assert!(!document.blocks.iter().any(|block| matches!(block, Block::Math { .. })));
+    let math: Vec<String> = document
+      .inlines_mut()
+      .flatten()
+      .filter_map(|inline| match inline {
+        Inline::Math { tex, .. } => Some(tex.clone()),
+        _ => None,
+      })
+      .collect();
+    assert_eq!(math, ["w"]);
+  }
+
+  #[test]
+  fn keeps_escaped_dollars_in_code_spans() {
+    assert_eq!(
+      parse_inlines("Type `\\$x` for \\$x", 0, 1),
+      [Inline::Text("Type `\\$x` for $x".to_string())]
+    );
+  }
+
+  #[test]
+  fn keeps_pages_starting_with_a_thematic_break() {
+    let document = parse(
+      "---\n\nIntro paragraph\n\n---\n\nBody\n",
+      Format::Markdown,
+      &ConversionOptions::default(),
+    );
+    assert_eq!(document.blocks.len(), 7);
+    assert!(matches!(document.blocks[0], Block::ThematicBreak { .. }));
+    let Block::Paragraph { content, .. } = &document.blocks[2] else {
+      panic!("Expected a paragraph, found {:?}", document.blocks[2]);
+    };
+    assert_eq!(inline_text(content), "Intro paragraph");
+    assert!(document.warnings.is_empty());
+
+    let document = parse(
+      "---\ntitle: Notes\nSee below\n---\n",
+      Format::Markdown,
+      &ConversionOptions::default(),
+    );
+    assert_eq!(document.metadata.title, None);
+    assert_eq!(document.warnings.len(), 1);
+    assert_eq!(
+      document.warnings[0].to_string(),
+      "line 3: not a 'key: value' pair, so the block at the start isn't read as front matter\n  See below"
+    );
+  }
+
+  #[test]
+  fn parses_markdown_extensions_when_enabled() {
+    let source =
+      "# HTML ^1^\nHTML\n: A markup language\n: Read by browsers\n\nCSS\n: Styles HTML\n\nH~2~O\n*[HTML]: HyperText\n";
+    let options = ConversionOptions {
+      definition_lists: true,
+      abbreviations: true,
+      superscript: true,
+      subscript: true,
+      ..Default::default()
+    };
+    let document = parse(source, Format::Markdown, &options);
+    let html = Inline::Abbreviation {
+      text: "HTML".to_string(),
+      title: "HyperText".to_string(),
+    };
+
+    assert_eq!(document.blocks.len(), 4);
+    assert_eq!(
+      document.blocks[0],
+      Block::Heading(Heading {
+        level: 1,
+        id: "html-1".to_string(),
+        content: vec![
+          html.clone(),
+          Inline::Text(" ".to_string()),
+          Inline::Superscript("1".to_string())
+        ],
+        span: Span {
+          start: 0,
+          end: 11,
+          line: 1
+        },
+      })
+    );
+    let Block::DefinitionList { items, span } = &document.blocks[1] else {
+      panic!("Expected a definition list, found {:?}", document.blocks[1]);
+    };
+    assert_eq!((span.line, span.end), (2, 74));
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0].term, vec![html.clone()]);
+    assert_eq!(items[0].definitions[1], [Inline::Text("Read by browsers".to_string())]);
+    assert_eq!(items[1].definitions, [vec![Inline::Text("Styles ".to_string()), html]]);
+    assert_eq!(
+      document.blocks[3],
+      Block::Paragraph {
+        content: vec![
+          Inline::Text("H".to_string()),
+          Inline::Subscript("2".to_string()),
+          Inline::Text("O".to_string())
+        ],
+        span: Span {
+          start: 75,
+          end: 81,
+          line: 9
+        },
+      }
+    );
+
+    // Every extension is off by default
+    let document = parse(source, Format::Markdown, &ConversionOptions::default());
+    assert_eq!(document.blocks.len(), 6);
+    assert!(matches!(&document.blocks[1], Block::Paragraph { content, .. } if content.len() == 5));
+  }
+
+  #[test]
+  fn parses_math_blocks_and_reports_bad_tex() {
+    let source = "$$\n\\frac{1}{2}\n$$\n\nSee $\\foo{x}$ here\n$$ x $$\n";
+    let document = parse(source, Format::Markdown, &ConversionOptions::default());
+
+    assert_eq!(
+      document.blocks[0],
+      Block::Math {
+        tex: "\\frac{1}{2}".to_string(),
+        span: Span {
+          start: 0,
+          end: 18,
+          line: 1,
+        },
+      }
+    );
+    assert!(matches!(&document.blocks[3], Block::Math { tex, .. } if tex == "x"));
+    assert_eq!(
+      document.warnings[0].to_string(),
+      "line 5: could not convert math, unknown command '\\foo'\n  See $\\foo{x}$ here"
+    );
+  }
+
+  #[test]
+  fn keeps_first_line_when_front_matter_has_the_title() {
+    let source = "---\ntitle: Front\n---\nImportant first paragraph\n\n\nSecond\n";
+    let document = parse(source, Format::Markdown, &ConversionOptions::default());
+
+    assert_eq!(document.title.as_ref().map(Heading::text).as_deref(), Some("Front"));
+    let Block::Paragraph { content, span } = &document.blocks[0] else {
+      panic!("Expected a paragraph, found {:?}", document.blocks[0]);
+    };
+    assert_eq!(inline_text(content), "Important first paragraph");
+    assert_eq!(span.line, 4);
+  }
+
+  #[test]
+  fn parses_text_title_chapters_and_toc() {
+    let source = "The Title\n\n\nI.\n\nFirst line\nsecond line\n";
+    let options = ConversionOptions {
+      chapters: true,
+      ..Default::default()
+    };
+    let document = parse(source, Format::Text, &options);
+
+    assert_eq!(document.metadata.title.as_deref(), Some("The Title"));
+    assert_eq!(document.title.as_ref().map(|title| title.span.line), Some(1));
+    assert!(matches!(document.blocks[0], Block::TableOfContents { .. }));
+    assert_eq!(document.headings().map(Heading::text).collect::<Vec<_>>(), ["I."]);
+    assert_eq!(document.blocks[3].span().line, 6);
+  }
+}
@@ -0,0 +1,378 @@
+use crate::typography;
+
+// Emoji for each shortcode name, sorted by name so they can be searched
+// Names follow the ones used by GitHub
+const EMOJI: &[(&str, &str)] = &[
+  ("+1", "👍"),
+  ("-1", "👎"),
+  ("100", "💯"),
+  ("airplane", "✈️"),
+  ("alarm_clock", "⏰"),
+  ("angry", "😠"),
+  ("ant", "🐜"),
+  ("apple", "🍎"),
+  ("arrow_down", "⬇️"),
+  ("arrow_left", "⬅️"),
+  ("arrow_right", "➡️"),
+  ("arrow_up", "⬆️"),
+  ("art", "🎨"),
+  ("astonished", "😲"),
+  ("baby", "👶"),
+  ("balloon", "🎈"),
+  ("bangbang", "‼️"),
+  ("bar_chart", "📊"),
+  ("beer", "🍺"),
+  ("bell", "🔔"),
+  ("bike", "🚲"),
+  ("bird", "🐦"),
+  ("birthday", "🎂"),
+  ("blue_heart", "💙"),
+  ("blush", "😊"),
+  ("bomb", "💣"),
+  ("book", "📖"),
+  ("books", "📚"),
+  ("boom", "💥"),
+  ("bow", "🙇"),
+  ("broken_heart", "💔"),
+  ("bug", "🐛"),
+  ("bulb", "💡"),
+  ("bus", "🚌"),
+  ("cake", "🍰"),
+  ("calendar", "📆"),
+  ("camera", "📷"),
+  ("car", "🚗"),
+  ("cat", "🐱"),
+  ("chart_with_downwards_trend", "📉"),
+  ("chart_with_upwards_trend", "📈"),
+  ("checkered_flag", "🏁"),
+  ("cherries", "🍒"),
+  ("clap", "👏"),
+  ("clipboard", "📋"),
+  ("clock", "🕒"),
+  ("cloud", "☁️"),
+  ("coffee", "☕"),
+  ("computer", "💻"),
+  ("confetti_ball", "🎊"),
+  ("confused", "😕"),
+  ("construction", "🚧"),
+  ("cookie", "🍪"),
+  ("cool", "🆒"),
+  ("cow", "🐮"),
+  ("crab", "🦀"),
+  ("cry", "😢"),
+  ("crystal_ball", "🔮"),
+  ("dart", "🎯"),
+  ("dash", "💨"),
+  ("dizzy", "💫"),
+  ("dog", "🐶"),
+  ("door", "🚪"),
+  ("dragon", "🐉"),
+  ("dress", "👗"),
+  ("earth_africa", "🌍"),
+  ("earth_americas", "🌎"),
+  ("earth_asia", "🌏"),
+  ("egg", "🥚"),
+  ("email", "📧"),
+  ("envelope", "✉️"),
+  ("eyes", "👀"),
+  ("facepunch", "👊"),
+  ("fire", "🔥"),
+  ("fish", "🐟"),
+  ("fist", "✊"),
+  ("flag_white", "🏳️"),
+  ("flashlight", "🔦"),
+  ("flower_playing_cards", "🎴"),
+  ("flushed", "😳"),
+  ("fork_and_knife", "🍴"),
+  ("four_leaf_clover", "🍀"),
+  ("frog", "🐸"),
+  ("frowning", "😦"),
+  ("gear", "⚙️"),
+  ("gem", "💎"),
+  ("ghost", "👻"),
+  ("gift", "🎁"),
+  ("globe_with_meridians", "🌐"),
+  ("grapes", "🍇"),
+  ("green_heart", "💚"),
+  ("grimacing", "😬"),
+  ("grin", "😁"),
+  ("grinning", "😀"),
+  ("guitar", "🎸"),
+  ("hammer", "🔨"),
+  ("hammer_and_wrench", "🛠️"),
+  ("hand", "✋"),
+  ("handshake", "🤝"),
+  ("hankey", "💩"),
+  ("headphones", "🎧"),
+  ("heart", "❤️"),
+  ("heart_eyes", "😍"),
+  ("heavy_check_mark", "✔️"),
+  ("heavy_minus_sign", "➖"),
+  ("heavy_plus_sign", "➕"),
+  ("hibiscus", "🌺"),
+  ("hourglass", "⌛"),
+  ("house", "🏠"),
+  ("hugs", "🤗"),
+  ("hushed", "😯"),
+  ("ice_cream", "🍨"),
+  ("id", "🆔"),
+  ("information_source", "ℹ️"),
+  ("innocent", "😇"),
+  ("joy", "😂"),
+  ("key", "🔑"),
+  ("keyboard", "⌨️"),
+  ("kiss", "😘"),
+  ("koala", "🐨"),
+  ("ladybug", "🐞"),
+  ("laptop", "💻"),
+  ("laughing", "😆"),
+  ("leaves", "🍃"),
+  ("lemon", "🍋"),
+  ("link", "🔗"),
+  ("lipstick", "💄"),
+  ("lock", "🔒"),
+  ("loudspeaker", "📢"),
+  ("mag", "🔍"),
+  ("mailbox", "📫"),
+  ("man", "👨"),
+  ("mask", "😷"),
+  ("medal_sports", "🏅"),
+  ("memo", "📝"),
+  ("microphone", "🎤"),
+  ("money_with_wings", "💸"),
+  ("monkey", "🐒"),
+  ("moon", "🌙"),
+  ("mortar_board", "🎓"),
+  ("mountain", "⛰️"),
+  ("muscle", "💪"),
+  ("mushroom", "🍄"),
+  ("musical_note", "🎵"),
+  ("new", "🆕"),
+  ("no_entry", "⛔"),
+  ("no_entry_sign", "🚫"),
+  ("notebook", "📓"),
+  ("nut_and_bolt", "🔩"),
+  ("ok", "🆗"),
+  ("ok_hand", "👌"),
+  ("open_mouth", "😮"),
+  ("orange_heart", "🧡"),
+  ("owl", "🦉"),
+  ("package", "📦"),
+  ("page_facing_up", "📄"),
+  ("palm_tree", "🌴"),
+  ("paperclip", "📎"),
+  ("partying_face", "🥳"),
+  ("pencil", "📝"),
+  ("pencil2", "✏️"),
+  ("penguin", "🐧"),
+  ("pensive", "😔"),
+  ("phone", "☎️"),
+  ("pig", "🐷"),
+  ("pill", "💊"),
+  ("pizza", "🍕"),
+  ("point_down", "👇"),
+  ("point_left", "👈"),
+  ("point_right", "👉"),
+  ("point_up", "👆"),
+  ("pray", "🙏"),
+  ("purple_heart", "💜"),
+  ("pushpin", "📌"),
+  ("question", "❓"),
+  ("rabbit", "🐰"),
+  ("rage", "😡"),
+  ("rainbow", "🌈"),
+  ("raised_hands", "🙌"),
+  ("recycle", "♻️"),
+  ("red_circle", "🔴"),
+  ("relaxed", "☺️"),
+  ("relieved", "😌"),
+  ("robot", "🤖"),
+  ("rocket", "🚀"),
+  ("rofl", "🤣"),
+  ("rose", "🌹"),
+  ("rotating_light", "🚨"),
+  ("runner", "🏃"),
+  ("sailboat", "⛵"),
+  ("santa", "🎅"),
+  ("satellite", "📡"),
+  ("scissors", "✂️"),
+  ("scream", "😱"),
+  ("see_no_evil", "🙈"),
+  ("seedling", "🌱"),
+  ("shield", "🛡️"),
+  ("ship", "🚢"),
+  ("shrug", "🤷"),
+  ("skull", "💀"),
+  ("sleeping", "😴"),
+  ("sleepy", "😪"),
+  ("slightly_frowning_face", "🙁"),
+  ("slightly_smiling_face", "🙂"),
+  ("smile", "😄"),
+  ("smiley", "😃"),
+  ("smirk", "😏"),
+  ("snail", "🐌"),
+  ("snake", "🐍"),
+  ("snowflake", "❄️"),
+  ("snowman", "⛄"),
+  ("sob", "😭"),
+  ("soccer", "⚽"),
+  ("sparkles", "✨"),
+  ("sparkling_heart", "💖"),
+  ("speech_balloon", "💬"),
+  ("star", "⭐"),
+  ("star2", "🌟"),
+  ("stars", "🌠"),
+  ("stopwatch", "⏱️"),
+  ("strawberry", "🍓"),
+  ("stuck_out_tongue", "😛"),
+  ("sun_with_face", "🌞"),
+  ("sunflower", "🌻"),
+  ("sunglasses", "😎"),
+  ("sunny", "☀️"),
+  ("sweat", "😓"),
+  ("sweat_smile", "😅"),
+  ("taco", "🌮"),
+  ("tada", "🎉"),
+  ("tent", "⛺"),
+  ("thinking", "🤔"),
+  ("thought_balloon", "💭"),
+  ("thumbsdown", "👎"),
+  ("thumbsup", "👍"),
+  ("ticket", "🎫"),
+  ("tiger", "🐯"),
+  ("tools", "🛠️"),
+  ("trophy", "🏆"),
+  ("truck", "🚚"),
+  ("tulip", "🌷"),
+  ("turtle", "🐢"),
+  ("tv", "📺"),
+  ("umbrella", "☂️"),
+  ("unamused", "😒"),
+  ("unicorn", "🦄"),
+  ("unlock", "🔓"),
+  ("v", "✌️"),
+  ("vertical_traffic_light", "🚦"),
+  ("video_game", "🎮"),
+  ("warning", "⚠️"),
+  ("watch", "⌚"),
+  ("wave", "👋"),
+  ("whale", "🐳"),
+  ("wheelchair", "♿"),
+  ("white_check_mark", "✅"),
+  ("wink", "😉"),
+  ("wrench", "🔧"),
+  ("x", "❌"),
+  ("yellow_heart", "💛"),
+  ("yum", "😋"),
+  ("zap", "⚡"),
+  ("zzz", "💤"),
+];
+
+/// Replace `:name:` emoji shortcodes in html with the emoji, leaving tags,
+/// `<code>`, `<pre>`, math, `<script>`, `<style>` and `<textarea>` elements,
+/// backtick spans and unknown names untouched
+pub fn replace_shortcodes(html: &str) -> String {
+  let mut replaced = String::with_capacity(html.len());
+  let mut code_depth = 0;
+  let mut rest = html;
+
+  while let Some(start) = rest.find(['<', '`', ':']) {
+    replaced.push_str(&rest[..start]);
+    rest = &rest[start..];
+
+    // Copy tags as is, keeping track of whether we're inside code, math or a
+    // script
+    if rest.starts_with('<') {
+      if let Some(tag_length) = rest.find('>') {
+        code_depth = typography::skipped_depth(&rest[..=tag_length], code_depth);
+        replaced.push_str(&rest[..=tag_length]);
+        rest = &rest[(tag_length + 1)..];
+        continue;
+      }
+    }
+
+    // Copy backtick code spans as is
+    if rest.starts_with('`') && code_depth == 0 {
+      if let Some(span_length) = rest[1..].find('`') {
+        replaced.push_str(&rest[..(span_length + 2)]);
+        rest = &rest[(span_length + 2)..];
+        continue;
+      }
+    }
+
+    if rest.starts_with(':') && code_depth == 0 {
+      if let Some((emoji, length)) = shortcode_at(rest, &replaced) {
+        replaced.push_str(emoji);
+        rest = &rest[length..];
+        continue;
+      }
+    }
+
+    replaced.push_str(&rest[..1]);
+    rest = &rest[1..];
+  }
+
+  replaced.push_str(rest);
+  replaced
+}
+
+// returns the emoji for a shortcode at the start of the text and the
+// shortcode's length, if it's a known name that isn't part of a longer word
+fn shortcode_at(text: &str, preceding_text: &str) -> Option<(&'static str, usize)> {
+  if preceding_text.ends_with(char::is_alphanumeric) {
+    return None;
+  }
+  let name_length = text[1..]
+    .find(|c: char| !(c.is_ascii_lowercase() || c.is_ascii_digit() || "_+-".contains(c)))
+    .unwrap_or(text.len() - 1);
+  let after_name = &text[(1 + name_length)..];
+  if name_length == 0 || !after_name.starts_with(':') || after_name[1..].starts_with(char::is_alphanumeric) {
+    return None;
+  }
+
+  let name = &text[1..(1 + name_length)];
+  let index = EMOJI.binary_search_by_key(&name, |&(name, _)| name).ok()?;
+  Some((EMOJI[index].1, name_length + 2))
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::emoji::*;
+
+  #[test]
+  fn replaces_known_shortcodes() {
+    assert_eq!(
+      replace_shortcodes("<p>\n\t\tShipped :rocket::tada: :+1: and :not_an_emoji:\n\t</p>"),
+      "<p>\n\t\tShipped 🚀🎉 👍 and :not_an_emoji:\n\t</p>"
+    );
+    assert_eq!(
+      replace_shortcodes("At 10:30:fire: or a:fire: b"),
+      "At 10:30:fire: or a:fire: b"
+    );
+  }
+
+  #[test]
+  fn leaves_code_and_tags_untouched() {
+    assert_eq!(
+      replace_shortcodes("<a title=\":fire:\">:fire:</a> `:fire:` <code>:fire:</code> <pre>\n:fire:\n</pre> :fire:"),
+      "<a title=\":fire:\">🔥</a> `:fire:` <code>:fire:</code> <pre>\n:fire:\n</pre> 🔥"
+    );
+  }
+
+  #[test]
+  fn leaves_scripts_and_styles_untouched() {
+    assert_eq!(
+      replace_shortcodes(
+        "<script>x = \":smile:\";</script><style>a::after { content: \":smile:\" \
+         }</style><textarea>:smile:</textarea> :smile:"
+      ),
+      "<script>x = \":smile:\";</script><style>a::after { content: \":smile:\" }</style><textarea>:smile:</textarea> \
+       😄"
+    );
+  }
+
+  #[test]
+  fn emoji_are_sorted_by_name() {
+    assert!(EMOJI.windows(2).all(|pair| pair[0].0 < pair[1].0));
+  }
+}
@@ -0,0 +1,159 @@
+use std::{fmt, io, path};
+
+// Exit codes follow the BSD sysexits.h convention, so scripts can tell the
+// kinds of failure apart
+const EXIT_CHECK_FAILED: i32 = 1;
+const EXIT_PARSE: i32 = 65;
+const EXIT_INPUT: i32 = 66;
+const EXIT_IO: i32 = 74;
+const EXIT_CONFIG: i32 = 78;
+
+/// Everything that can make a command fail
+#[derive(Debug)]
+pub enum Error {
+  /// The config file, environment variables or flags are invalid
+  Config {
+    path: Option<path::PathBuf>,
+    message: String,
+  },
+  /// The input is missing or isn't something that can be converted
+  Input {
+    path: Option<path::PathBuf>,
+    message: String,
+  },
+  /// Reading or writing a file failed
+  Io {
+    path: Option<path::PathBuf>,
+    action: String,
+    cause: io::Error,
+  },
+  /// A file was read, but its contents couldn't be used e.g. it isn't UTF-8
+  Parse { path: path::PathBuf, message: String },
+  /// Some files in the input couldn't be converted
+  Build(Vec<Error>),
+  /// The command ran, but found problems e.g. broken links
+  Check(String),
+}
+
+impl Error {
+  pub fn io(action: &str, path: impl Into<path::PathBuf>, cause: io::Error) -> Self {
+    Error::Io {
+      path: Some(path.into()),
+      action: action.to_string(),
+      cause,
+    }
+  }
+
+  /// No input was given on the command line, in the config or in the
+  /// environment
+  pub fn missing_input() -> Self {
+    Error::Input {
+      path: None,
+      message: "Missing required value 'input': use -i INPUT_PATH, or set input in the config file or ROST_GEN_INPUT"
+        .to_string(),
+    }
+  }
+
+  pub fn exit_code(&self) -> i32 {
+    match self {
+      Error::Config { .. } => EXIT_CONFIG,
+      Error::Input { .. } => EXIT_INPUT,
+      Error::Io { .. } => EXIT_IO,
+      Error::Parse { .. } => EXIT_PARSE,
+      // Use the code of the first failure, so a build where files couldn't be
+      // read still exits with the IO code
+      Error::Build(failures) => failures.first().map_or(EXIT_CHECK_FAILED, Error::exit_code),
+      Error::Check(_) => EXIT_CHECK_FAILED,
+    }
+  }
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Error::Config {
+        path: Some(path),
+        message,
+      } => write!(formatter, "Invalid config in '{}':\n  {message}", path.display()),
+      Error::Config { path: None, message } => write!(formatter, "Invalid config:\n  {message}"),
+      Error::Input {
+        path: Some(path),
+        message,
+      } => write!(formatter, "Invalid input at '{}': {message}", path.display()),
+      Error::Input { path: None, message } => write!(formatter, "{message}"),
+      Error::Io {
+        path: Some(path),
+        action,
+        cause,
+      } => write!(formatter, "Could not {action} '{}': {cause}", path.display()),
+      Error::Io {
+        path: None,
+        action,
+        cause,
+      } => write!(formatter, "Could not {action}: {cause}"),
+      Error::Parse { path, message } => write!(formatter, "Could not parse '{}': {message}", path.display()),
+      Error::Build(failures) => write!(formatter, "{} file(s) could not be converted", failures.len()),
+      Error::Check(message) => write!(formatter, "{message}"),
+    }
+  }
+}
+
+impl std::error::Error for Error {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      Error::Io { cause, .. } => Some(cause),
+      _ => None,
+    }
+  }
+}
+
+/// Read a text file, telling files that can't be read apart from files that
+/// aren't UTF-8
+pub fn read_text_file(action: &str, path: &path::Path) -> Result<String, Error> {
+  std::fs::read_to_string(path).map_err(|cause| {
+    if cause.kind() == io::ErrorKind::InvalidData {
+      Error::Parse {
+        path: path.to_path_buf(),
+        message: "it is not valid UTF-8 text".to_string(),
+      }
+    } else {
+      Error::io(action, path, cause)
+    }
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs;
+
+  use crate::error::*;
+
+  #[test]
+  fn describes_errors_with_paths_and_causes() {
+    let error = Error::io(
+      "read",
+      "docs/missing.txt",
+      io::Error::new(io::ErrorKind::NotFound, "not found"),
+    );
+
+    assert_eq!(error.to_string(), "Could not read 'docs/missing.txt': not found");
+    assert_eq!(error.exit_code(), EXIT_IO);
+    assert!(std::error::Error::source(&error).is_some());
+  }
+
+  #[test]
+  fn reports_non_utf8_files_as_parse_errors() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let file_path = temp_dir.path().join("binary.txt");
+    fs::write(&file_path, [0xff, 0xfe, 0x00]).expect("Create test file");
+
+    let error = read_text_file("read", &file_path).unwrap_err();
+    assert!(matches!(error, Error::Parse { .. }));
+    assert_eq!(error.exit_code(), EXIT_PARSE);
+
+    let error = read_text_file("read", &temp_dir.path().join("missing.txt")).unwrap_err();
+    assert_eq!(error.exit_code(), EXIT_IO);
+
+    temp_dir.close().expect("Delete test directory");
+  }
+}
@@ -0,0 +1,207 @@
+use crate::document::Inline;
+
+/// `: definition`, a line defining the term on the line before it
+/// returns where the definition starts in the line and the definition without
+/// the colon
+pub(crate) fn parse_definition(line: &str) -> Option<(usize, &str)> {
+  let colon = line.len() - line.trim_start().len();
+  let definition = line[colon..].strip_prefix(':')?;
+  let start = colon + 1 + (definition.len() - definition.trim_start().len());
+  definition
+    .starts_with([' ', '\t'])
+    .then(|| (start, definition.trim()))
+    .filter(|(_, definition)| !definition.is_empty())
+}
+
+/// `*[HTML]: HyperText Markup Language`, defining an abbreviation used in the
+/// rest of the page
+/// returns the abbreviation and what it stands for
+pub(crate) fn parse_abbreviation(line: &str) -> Option<(String, String)> {
+  let (abbreviation, title) = line.trim().strip_prefix("*[")?.split_once("]:")?;
+  let (abbreviation, title) = (abbreviation.trim(), title.trim());
+  (!abbreviation.is_empty() && !title.is_empty()).then(|| (abbreviation.to_string(), title.to_string()))
+}
+
+/// Split text into text and `^superscript^` or `~subscript~`, which can't
+/// contain spaces
+/// `\^` and `\~` are written as `^` and `~`, and text in html tags and
+/// backtick spans is left as is
+pub(crate) fn split_scripts(text: &str, superscript: bool, subscript: bool) -> Vec<Inline> {
+  let mut inlines = Vec::new();
+  let mut plain_text = String::new();
+  let mut rest = text;
+
+  while let Some(start) = rest.find(['\\', '`', '<', '^', '~']) {
+    plain_text.push_str(&rest[..start]);
+    rest = &rest[start..];
+    let enabled = |marker: char| (marker == '^' && superscript) || (marker == '~' && subscript);
+
+    match rest.as_bytes()[0] {
+      b'\\' if rest[1..].starts_with(enabled) => {
+        plain_text.push_str(&rest[1..2]);
+        rest = &rest[2..];
+        continue;
+      }
+      marker @ (b'`' | b'<') => {
+        let closing_marker = if marker == b'`' { '`' } else { '>' };
+        if let Some(length) = rest[1..].find(closing_marker) {
+          plain_text.push_str(&rest[..(length + 2)]);
+          rest = &rest[(length + 2)..];
+          continue;
+        }
+      }
+      b'^' if superscript => {
+        if let Some(length) = script_length(rest, '^') {
+          push_text(&mut inlines, &mut plain_text);
+          inlines.push(Inline::Superscript(rest[1..(length + 1)].to_string()));
+          rest = &rest[(length + 2)..];
+          continue;
+        }
+      }
+      // `~~` is left for strikethrough
+      b'~' if subscript && !plain_text.ends_with('~') => {
+        if let Some(length) = script_length(rest, '~') {
+          push_text(&mut inlines, &mut plain_text);
+          inlines.push(Inline::Subscript(rest[1..(length + 1)].to_string()));
+          rest = &rest[(length + 2)..];
+          continue;
+        }
+      }
+      _ => {}
+    }
+
+    plain_text.push_str(&rest[..1]);
+    rest = &rest[1..];
+  }
+
+  plain_text.push_str(rest);
+  push_text(&mut inlines, &mut plain_text);
+  inlines
+}
+
+// Length of the text between a marker at the start of the text and the next
+// one, if there's no space between them
+fn script_length(text: &str, marker: char) -> Option<usize> {
+  let inner = &text[1..];
+  let length = inner.find(|char: char| char == marker || char.is_whitespace())?;
+  let after = &inner[length..];
+  let doubled = |text: &str| marker == '~' && text.starts_with(marker);
+  (length > 0 && after.starts_with(marker) && !doubled(inner) && !doubled(&after[1..])).then_some(length)
+}
+
+/// Split text into text and each abbreviation that appears as a word of its
+/// own, leaving text in html tags and backtick spans as is
+/// Abbreviations are matched in order, so longer ones should come first
+pub(crate) fn split_abbreviations(text: &str, abbreviations: &[(String, String)]) -> Vec<Inline> {
+  let mut inlines = Vec::new();
+  let mut text_start = 0;
+  let mut i = 0;
+
+  while i < text.len() {
+    let rest = &text[i..];
+    let closing_marker = match rest.as_bytes()[0] {
+      b'`' => Some('`'),
+      b'<' => Some('>'),
+      _ => None,
+    };
+    if let Some(length) = closing_marker.and_then(|marker| rest[1..].find(marker)) {
+      i += length + 2;
+      continue;
+    }
+
+    let abbreviation = abbreviations.iter().find(|(abbreviation, _)| {
+      rest.starts_with(abbreviation.as_str())
+        && !text[..i].ends_with(char::is_alphanumeric)
+        && !rest[abbreviation.len()..].starts_with(char::is_alphanumeric)
+    });
+    match abbreviation {
+      Some((abbreviation, title)) => {
+        if text_start < i {
+          inlines.push(Inline::Text(text[text_start..i].to_string()));
+        }
+        inlines.push(Inline::Abbreviation {
+          text: abbreviation.clone(),
+          title: title.clone(),
+        });
+        i += abbreviation.len();
+        text_start = i;
+      }
+      None => i += rest.chars().next().map_or(1, char::len_utf8),
+    }
+  }
+
+  if text_start < text.len() {
+    inlines.push(Inline::Text(text[text_start..].to_string()));
+  }
+  inlines
+}
+
+fn push_text(inlines: &mut Vec<Inline>, text: &mut String) {
+  if !text.is_empty() {
+    inlines.push(Inline::Text(std::mem::take(text)));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::extensions::*;
+
+  #[test]
+  fn parses_definitions_and_abbreviations() {
+    assert_eq!(
+      parse_definition(": A markup language\n"),
+      Some((2, "A markup language"))
+    );
+    assert_eq!(parse_definition("  :\tIndented"), Some((4, "Indented")));
+    assert_eq!(parse_definition(":::note"), None);
+    assert_eq!(parse_definition("Time: 10:30"), None);
+    assert_eq!(
+      parse_abbreviation("*[HTML]: HyperText Markup Language\n"),
+      Some(("HTML".to_string(), "HyperText Markup Language".to_string()))
+    );
+    assert_eq!(parse_abbreviation("*[HTML]:"), None);
+    assert_eq!(parse_abbreviation("[HTML]: html.html"), None);
+  }
+
+  #[test]
+  fn splits_superscript_and_subscript() {
+    assert_eq!(
+      split_scripts("H~2~O and 2^10^ but not x^2 + y ^ or ~~gone~~ or `a^b^`", true, true),
+      [
+        Inline::Text("H".to_string()),
+        Inline::Subscript("2".to_string()),
+        Inline::Text("O and 2".to_string()),
+        Inline::Superscript("10".to_string()),
+        Inline::Text(" but not x^2 + y ^ or ~~gone~~ or `a^b^`".to_string()),
+      ]
+    );
+    assert_eq!(
+      split_scripts("\\^x^ and H~2~O", true, false),
+      [Inline::Text("^x^ and H~2~O".to_string())]
+    );
+  }
+
+  #[test]
+  fn splits_abbreviations_in_whole_words() {
+    let abbreviations = [
+      ("HTML5".to_string(), "HTML version 5".to_string()),
+      ("HTML".to_string(), "HyperText Markup Language".to_string()),
+    ];
+
+    assert_eq!(
+      split_abbreviations("HTML and HTML5, not XHTML or <a title=\"HTML\">", &abbreviations),
+      [
+        Inline::Abbreviation {
+          text: "HTML".to_string(),
+          title: "HyperText Markup Language".to_string(),
+        },
+        Inline::Text(" and ".to_string()),
+        Inline::Abbreviation {
+          text: "HTML5".to_string(),
+          title: "HTML version 5".to_string(),
+        },
+        Inline::Text(", not XHTML or <a title=\"HTML\">".to_string()),
+      ]
+    );
+  }
+}
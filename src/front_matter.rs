@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+
+/// `key: value` pairs from a block delimited by `---` lines at the very start
+/// of a file
+#[derive(Debug, Default)]
+pub struct FrontMatter {
+  values: HashMap<String, String>,
+  /// Lines that aren't `key: value` pairs in a block that has some, by line
+  /// number, which stop the block being read as front matter
+  pub unparsed_lines: Vec<(usize, String)>,
+}
+
+impl FrontMatter {
+  pub fn get(&self, key: &str) -> Option<&str> { self.values.get(key).map(String::as_str) }
+
+  pub fn values(&self) -> &HashMap<String, String> { &self.values }
+
+  /// Returns None if the key is missing or isn't a true/false value
+  pub fn flag(&self, key: &str) -> Option<bool> {
+    match self.get(key)?.to_lowercase().as_str() {
+      "true" | "yes" | "on" => Some(true),
+      "false" | "no" | "off" => Some(false),
+      _ => None,
+    }
+  }
+}
+
+/// Returns the front matter and the number of bytes it takes up, or an empty
+/// front matter and 0 if the file doesn't start with one
+/// A block with lines that aren't `key: value` pairs is part of the page,
+/// e.g. text between two thematic breaks, so it isn't read as front matter
+pub fn parse_front_matter(contents: &str) -> (FrontMatter, usize) {
+  let mut lines = contents.split_inclusive('\n').enumerate();
+  let mut front_matter = FrontMatter::default();
+
+  let mut read_bytes = match lines.next() {
+    Some((_, line)) if line.trim_end() == "---" => line.len(),
+    _ => return (front_matter, 0),
+  };
+
+  for (index, line) in lines {
+    read_bytes += line.len();
+
+    if line.trim_end() == "---" {
+      if front_matter.values.is_empty() {
+        break;
+      }
+      // The lines that couldn't be read are reported when the block had
+      // some values, as it was probably meant to be front matter
+      if !front_matter.unparsed_lines.is_empty() {
+        front_matter.values.clear();
+        return (front_matter, 0);
+      }
+      return (front_matter, read_bytes);
+    }
+
+    match parse_key_value(line) {
+      Some((key, value)) => {
+        front_matter.values.insert(key, value);
+      }
+      None if line.trim().is_empty() => {}
+      None => front_matter
+        .unparsed_lines
+        .push((index + 1, line.trim_end_matches(['\n', '\r']).to_string())),
+    }
+  }
+
+  // No closing delimiter or no values, so this was just a thematic break
+  (FrontMatter::default(), 0)
+}
+
+// returns the lowercase key and the value without quotes, if the key is a
+// single word
+// Quotes and backslashes in double quoted values are escaped with a backslash
+fn parse_key_value(line: &str) -> Option<(String, String)> {
+  let (key, value) = line.split_once(':')?;
+  let key = key.trim();
+  let key_valid = !key.is_empty()
+    && key
+      .chars()
+      .all(|char| char.is_alphanumeric() || char == '_' || char == '-');
+  if !key_valid {
+    return None;
+  }
+
+  let value = value.trim();
+  let value = match value.strip_prefix('"').and_then(|value| value.strip_suffix('"')) {
+    Some(quoted_value) => unescape(quoted_value),
+    None => value.to_string(),
+  };
+  Some((key.to_lowercase(), value))
+}
+
+fn unescape(value: &str) -> String {
+  let mut unescaped = String::with_capacity(value.len());
+  let mut chars = value.chars().peekable();
+  while let Some(char) = chars.next() {
+    match (char, chars.peek()) {
+      ('\\', Some(&escaped @ ('"' | '\\'))) => {
+        unescaped.push(escaped);
+        chars.next();
+      }
+      _ => unescaped.push(char),
+    }
+  }
+  unescaped
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::front_matter::*;
+
+  #[test]
+  fn parses_front_matter_values() {
+    let contents = "---\ntitle: \"My page\"\ntoc: true\n---\n# Heading\n";
+    let (front_matter, read_bytes) = parse_front_matter(contents);
+
+    assert_eq!(front_matter.get("title"), Some("My page"));
+    assert_eq!(front_matter.flag("toc"), Some(true));
+    assert_eq!(&contents[read_bytes..], "# Heading\n");
+  }
+
+  #[test]
+  fn unescapes_double_quoted_values() {
+    let (front_matter, _) = parse_front_matter("---\ntitle: \"A \\\"quote\\\" \\\\ \\n\"\nplain: a \\\"b\\\"\n---\n");
+
+    assert_eq!(front_matter.get("title"), Some("A \"quote\" \\ \\n"));
+    assert_eq!(front_matter.get("plain"), Some("a \\\"b\\\""));
+  }
+
+  #[test]
+  fn ignores_unclosed_front_matter() {
+    let (front_matter, read_bytes) = parse_front_matter("---\nSome text: after a break\n");
+
+    assert_eq!(front_matter.get("some text"), None);
+    assert_eq!(read_bytes, 0);
+  }
+
+  #[test]
+  fn keeps_text_between_thematic_breaks() {
+    let (front_matter, read_bytes) = parse_front_matter("---\n\nIntro paragraph\n\n---\n\nBody\n");
+    assert!(front_matter.values().is_empty());
+    assert!(front_matter.unparsed_lines.is_empty());
+    assert_eq!(read_bytes, 0);
+
+    let (front_matter, read_bytes) = parse_front_matter("---\ntitle: Notes\nSee the notes below\n---\n");
+    assert!(front_matter.values().is_empty());
+    assert_eq!(front_matter.unparsed_lines, [(3, "See the notes below".to_string())]);
+    assert_eq!(read_bytes, 0);
+  }
+
+  #[test]
+  fn ignores_files_without_front_matter() {
+    let (front_matter, read_bytes) = parse_front_matter("# Heading\n---\n");
+
+    assert_eq!(front_matter.flag("toc"), None);
+    assert_eq!(read_bytes, 0);
+  }
+}
@@ -0,0 +1,121 @@
+/// The text of a Project Gutenberg ebook without its header and licence, along
+/// with the metadata found in the header
+pub struct GutenbergText<'a> {
+  pub title: Option<String>,
+  pub author: Option<String>,
+  pub body: &'a str,
+  /// Where the body starts in the ebook text
+  pub body_start: usize,
+}
+
+/// Returns None if the start of the ebook text isn't marked with a
+/// `*** START OF THE PROJECT GUTENBERG EBOOK ... ***` line
+pub fn strip_gutenberg(contents: &str) -> Option<GutenbergText<'_>> {
+  let mut title = None;
+  let mut author = None;
+  let mut read_bytes = 0;
+  let mut body_start = None;
+  let mut body_end = contents.len();
+
+  for line in contents.split_inclusive('\n') {
+    let line_start = read_bytes;
+    read_bytes += line.len();
+
+    if body_start.is_none() {
+      if is_marker(line, "START OF") {
+        body_start = Some(read_bytes);
+      } else if let Some(value) = header_value(line, "Title") {
+        title = Some(value);
+      } else if let Some(value) = header_value(line, "Author") {
+        author = Some(value);
+      }
+    } else if is_marker(line, "END OF") || is_end_of_ebook_line(line) {
+      body_end = line_start;
+      break;
+    }
+  }
+
+  let body_start = body_start?;
+  let (blank_lines_length, body) = trim_blank_lines(&contents[body_start..body_end]);
+  Some(GutenbergText {
+    title,
+    author,
+    body,
+    body_start: body_start + blank_lines_length,
+  })
+}
+
+// Markers look like `*** START OF THE PROJECT GUTENBERG EBOOK SILVER BLAZE ***`
+// or `*** END OF THIS PROJECT GUTENBERG EBOOK ... ***`
+fn is_marker(line: &str, marker: &str) -> bool {
+  let line = line.trim().to_uppercase();
+  line.trim_start_matches('*').trim_start().starts_with(marker) && line.contains("PROJECT GUTENBERG")
+}
+
+// Older ebooks end the text with a line like `End of the Project Gutenberg
+// EBook of ...` before the licence
+fn is_end_of_ebook_line(line: &str) -> bool {
+  let line = line.trim().to_uppercase();
+  line.starts_with("END OF THE PROJECT GUTENBERG") || line.starts_with("END OF PROJECT GUTENBERG")
+}
+
+fn header_value(line: &str, key: &str) -> Option<String> {
+  let (line_key, value) = line.split_once(':')?;
+  let value = value.trim();
+
+  if line_key.trim() == key && !value.is_empty() {
+    Some(value.to_string())
+  } else {
+    None
+  }
+}
+
+// Remove blank lines around the text, keeping the line ending of the last line
+// returns the length of the blank lines removed from the start and the text
+fn trim_blank_lines(text: &str) -> (usize, &str) {
+  let trimmed_text = text.trim_start_matches(['\r', '\n']);
+  let blank_lines_length = text.len() - trimmed_text.len();
+  let text_end = trimmed_text.trim_end().len();
+
+  match trimmed_text[text_end..].find('\n') {
+    Some(newline) => (blank_lines_length, &trimmed_text[..(text_end + newline + 1)]),
+    None => (blank_lines_length, trimmed_text),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::gutenberg::*;
+
+  const EBOOK: &str = "The Project Gutenberg eBook of Silver Blaze\n\nTitle: Silver Blaze\n\nAuthor: Arthur Conan \
+                       Doyle\n\n*** START OF THE PROJECT GUTENBERG EBOOK SILVER BLAZE ***\n\n\nI am afraid, \
+                       Watson.\n\n\n*** END OF THE PROJECT GUTENBERG EBOOK SILVER BLAZE ***\n\nSection 1. General \
+                       Terms of Use\n";
+
+  #[test]
+  fn strips_header_and_licence() {
+    let text = strip_gutenberg(EBOOK).unwrap();
+    assert_eq!(text.body, "I am afraid, Watson.\n");
+    assert_eq!(&EBOOK[text.body_start..][..text.body.len()], text.body);
+  }
+
+  #[test]
+  fn extracts_title_and_author() {
+    let text = strip_gutenberg(EBOOK).unwrap();
+    assert_eq!(text.title.as_deref(), Some("Silver Blaze"));
+    assert_eq!(text.author.as_deref(), Some("Arthur Conan Doyle"));
+  }
+
+  #[test]
+  fn strips_older_end_of_ebook_line() {
+    let ebook = "*** START OF THIS PROJECT GUTENBERG EBOOK X ***\nText\r\nEnd of the Project Gutenberg EBook of X\n";
+    let text = strip_gutenberg(ebook).unwrap();
+    assert_eq!(text.body, "Text\r\n");
+    assert_eq!(text.title, None);
+  }
+
+  #[test]
+  fn ignores_text_without_start_marker() {
+    assert!(strip_gutenberg("Title: Not an ebook\n\nText\n").is_none());
+  }
+}
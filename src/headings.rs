@@ -0,0 +1,146 @@
+use std::collections::HashSet;
+
+/// A heading that should be listed in a page's table of contents
+pub struct TocEntry {
+  pub level: usize,
+  pub id: String,
+  pub text: String,
+}
+
+/// Hands out heading ids, making sure each one is only used once per page
+#[derive(Default)]
+pub struct HeadingIds {
+  used: HashSet<String>,
+}
+
+impl HeadingIds {
+  pub fn unique_id(&mut self, heading_text: &str) -> String {
+    let slug = slugify(heading_text);
+    let mut id = slug.clone();
+    let mut suffix = 1;
+
+    // Repeated headings get a numbered suffix e.g. intro, intro-1, intro-2
+    while self.used.contains(&id) {
+      id = format!("{slug}-{suffix}");
+      suffix += 1;
+    }
+
+    self.used.insert(id.clone());
+    id
+  }
+}
+
+/// Returns the level and text of a markdown heading line e.g. `## Text`
+pub fn parse_heading(line: &str) -> Option<(usize, &str)> {
+  let level = line.bytes().take_while(|&char| char == b'#').count();
+
+  if (1..=6).contains(&level) {
+    line[level..].strip_prefix(' ').map(|text| (level, text))
+  } else {
+    None
+  }
+}
+
+/// Lowercase the heading text, keeping letters and digits from any script and
+/// replacing whitespace with hyphens
+pub fn slugify(text: &str) -> String {
+  let mut slug = String::new();
+
+  for char in text.trim().chars() {
+    if char.is_alphanumeric() || char == '_' {
+      slug.extend(char.to_lowercase());
+    } else if (char.is_whitespace() || char == '-') && !slug.is_empty() && !slug.ends_with('-') {
+      slug.push('-');
+    }
+  }
+
+  let slug = slug.trim_end_matches('-');
+  if slug.is_empty() {
+    "section".to_string()
+  } else {
+    slug.to_string()
+  }
+}
+
+/// Render headings as nested lists, one level of nesting per heading level
+pub fn render_toc(entries: &[TocEntry]) -> String {
+  let mut html = String::from("<nav class=\"toc\">\n");
+  let mut open_levels: Vec<usize> = Vec::new();
+
+  for entry in entries {
+    // Close lists for headings deeper than this one
+    while open_levels.last().is_some_and(|&level| level > entry.level) {
+      html.push_str("</li>\n</ul>\n");
+      open_levels.pop();
+    }
+
+    if open_levels.last() == Some(&entry.level) {
+      html.push_str("</li>\n");
+    } else {
+      html.push_str("<ul>\n");
+      open_levels.push(entry.level);
+    }
+
+    html.push_str(&format!("<li><a href=\"#{}\">{}</a>", entry.id, entry.text));
+  }
+
+  while open_levels.pop().is_some() {
+    html.push_str("</li>\n</ul>\n");
+  }
+
+  html.push_str("</nav>\n");
+  html
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::headings::*;
+
+  fn toc_entry(level: usize, id: &str) -> TocEntry {
+    TocEntry {
+      level,
+      id: id.to_string(),
+      text: id.to_string(),
+    }
+  }
+
+  #[test]
+  fn slugifies_heading_text() {
+    assert_eq!(slugify("Hello, World!"), "hello-world");
+    assert_eq!(slugify("  Step 1 -- Install  "), "step-1-install");
+    assert_eq!(slugify("Ça va? Überprüfung"), "ça-va-überprüfung");
+    assert_eq!(slugify("日本語 見出し"), "日本語-見出し");
+    assert_eq!(slugify("???"), "section");
+  }
+
+  #[test]
+  fn deduplicates_heading_ids() {
+    let mut heading_ids = HeadingIds::default();
+    assert_eq!(heading_ids.unique_id("Intro"), "intro");
+    assert_eq!(heading_ids.unique_id("Intro"), "intro-1");
+    assert_eq!(heading_ids.unique_id("Intro 1"), "intro-1-1");
+    assert_eq!(heading_ids.unique_id("Intro"), "intro-2");
+  }
+
+  #[test]
+  fn parses_heading_levels() {
+    assert_eq!(parse_heading("# Title\n"), Some((1, "Title\n")));
+    assert_eq!(parse_heading("### Sub title\n"), Some((3, "Sub title\n")));
+    assert_eq!(parse_heading("####### Too deep\n"), None);
+    assert_eq!(parse_heading("#hashtag\n"), None);
+  }
+
+  #[test]
+  fn renders_nested_toc() {
+    let entries = [
+      toc_entry(1, "a"),
+      toc_entry(2, "b"),
+      toc_entry(2, "c"),
+      toc_entry(1, "d"),
+    ];
+    let expected_output = "<nav class=\"toc\">\n<ul>\n<li><a href=\"#a\">a</a><ul>\n<li><a \
+                           href=\"#b\">b</a></li>\n<li><a href=\"#c\">c</a></li>\n</ul>\n</li>\n<li><a \
+                           href=\"#d\">d</a></li>\n</ul>\n</nav>\n";
+    assert_eq!(render_toc(&entries), expected_output);
+  }
+}
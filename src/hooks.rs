@@ -0,0 +1,309 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt, path::Path};
+
+use crate::document::{Document, Inline, Span};
+use crate::{ConversionOptions, Error, Format, OutputFormat};
+
+/// The page a hook is run on
+pub struct PageContext<'a> {
+  /// The input file, or None for text converted with `convert_str`
+  pub path: Option<&'a Path>,
+  pub format: Format,
+  pub options: &'a ConversionOptions,
+}
+
+/// Changes pages at each stage of a build
+///
+/// Every stage does nothing by default, so a hook only needs to implement the
+/// stages it uses
+pub trait Hook {
+  /// Shown when the options are printed
+  fn name(&self) -> &str { std::any::type_name::<Self>() }
+
+  /// Change the text of a page before it's parsed
+  fn pre_parse(&self, source: String, _page: &PageContext) -> String { source }
+
+  /// Change the parsed page before it's rendered
+  fn post_parse(&self, _document: &mut Document, _page: &PageContext) {}
+
+  /// Change the rendered page before it's written
+  fn post_render(&self, output: String, _page: &PageContext) -> String { output }
+
+  /// Run once after every page and the sitemap have been written
+  fn finalise(&self, _output_dir: &Path) -> Result<(), Error> { Ok(()) }
+}
+
+/// Hooks run on every page, in the order they were registered
+#[derive(Default)]
+pub struct Hooks {
+  hooks: Vec<Box<dyn Hook>>,
+}
+
+impl Hooks {
+  /// The built-in hooks enabled in a config
+  pub fn from_config(builtin_hooks: Vec<BuiltinHook>) -> Self {
+    let mut hooks = Hooks::default();
+    for hook in builtin_hooks {
+      hooks.register(hook);
+    }
+    hooks
+  }
+
+  pub fn register(&mut self, hook: impl Hook + 'static) { self.hooks.push(Box::new(hook)); }
+
+  pub fn is_empty(&self) -> bool { self.hooks.is_empty() }
+
+  pub fn pre_parse(&self, source: String, page: &PageContext) -> String {
+    self
+      .hooks
+      .iter()
+      .fold(source, |source, hook| hook.pre_parse(source, page))
+  }
+
+  pub fn post_parse(&self, document: &mut Document, page: &PageContext) {
+    for hook in &self.hooks {
+      hook.post_parse(document, page);
+    }
+  }
+
+  pub fn post_render(&self, output: String, page: &PageContext) -> String {
+    self
+      .hooks
+      .iter()
+      .fold(output, |output, hook| hook.post_render(output, page))
+  }
+
+  /// Stops at the first hook that fails
+  pub fn finalise(&self, output_dir: &Path) -> Result<(), Error> {
+    self.hooks.iter().try_for_each(|hook| hook.finalise(output_dir))
+  }
+}
+
+impl fmt::Debug for Hooks {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    formatter
+      .debug_list()
+      .entries(self.hooks.iter().map(|hook| hook.name()))
+      .finish()
+  }
+}
+
+/// Hooks that can be enabled in the `hooks` list of a config file
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "name", rename_all = "snake_case", deny_unknown_fields)]
+pub enum BuiltinHook {
+  /// Replace text in each page before it's parsed
+  Replace { find: String, replace: String },
+  /// Link ids like JIRA-123, where `{id}` in the url is replaced with the id
+  Autolink { prefix: String, url: String },
+  /// Add html at the start of each html page's body
+  Banner { html: String },
+}
+
+impl Hook for BuiltinHook {
+  fn name(&self) -> &str {
+    match self {
+      BuiltinHook::Replace { .. } => "replace",
+      BuiltinHook::Autolink { .. } => "autolink",
+      BuiltinHook::Banner { .. } => "banner",
+    }
+  }
+
+  fn pre_parse(&self, source: String, _page: &PageContext) -> String {
+    match self {
+      BuiltinHook::Replace { find, replace } if !find.is_empty() => source.replace(find, replace),
+      _ => source,
+    }
+  }
+
+  fn post_parse(&self, document: &mut Document, _page: &PageContext) {
+    if let BuiltinHook::Autolink { prefix, url } = self {
+      if prefix.is_empty() {
+        return;
+      }
+      for content in document.inlines_mut() {
+        *content = content
+          .drain(..)
+          .flat_map(|inline| match inline {
+            Inline::Text(text) => autolink(&text, prefix, url),
+            inline => vec![inline],
+          })
+          .collect();
+      }
+    }
+  }
+
+  fn post_render(&self, output: String, page: &PageContext) -> String {
+    match self {
+      BuiltinHook::Banner { html } if page.options.output_format == OutputFormat::Html => {
+        insert_after_body_tag(output, html)
+      }
+      _ => output,
+    }
+  }
+}
+
+// Split text into text and links for each prefix followed by a number, which
+// isn't part of a longer word
+// Links added here weren't in the source, so they have an empty span
+fn autolink(text: &str, prefix: &str, url: &str) -> Vec<Inline> {
+  let mut inlines = Vec::new();
+  let mut text_start = 0;
+  let mut search_start = 0;
+
+  while let Some(found) = text[search_start..].find(prefix) {
+    let start = search_start + found;
+    let number_start = start + prefix.len();
+    let number_length = text[number_start..]
+      .find(|c: char| !c.is_ascii_digit())
+      .unwrap_or(text.len() - number_start);
+    search_start = number_start;
+    if number_length == 0 || text[..start].ends_with(|c: char| c.is_alphanumeric()) {
+      continue;
+    }
+
+    let end = number_start + number_length;
+    if text_start < start {
+      inlines.push(Inline::Text(text[text_start..start].to_string()));
+    }
+    let id = &text[start..end];
+    inlines.push(Inline::Link {
+      text: id.to_string(),
+      url: url.replace("{id}", id),
+      span: Span::default(),
+    });
+    text_start = end;
+    search_start = end;
+  }
+
+  if text_start < text.len() {
+    inlines.push(Inline::Text(text[text_start..].to_string()));
+  }
+  inlines
+}
+
+// Pages from templates without a body tag get the html at the start instead
+fn insert_after_body_tag(mut page: String, html: &str) -> String {
+  let body_tag_end = page
+    .find("<body")
+    .and_then(|start| page[start..].find('>').map(|end| start + end + 1));
+  match body_tag_end {
+    Some(index) => {
+      page.insert_str(index, &format!("\n\t{html}"));
+      page
+    }
+    None => format!("{html}\n{page}"),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::hooks::*;
+  use crate::{build, convert_str, BuildSettings};
+  use std::cell::Cell;
+  use std::fs;
+  use std::rc::Rc;
+
+  fn options_with(builtin_hooks: Vec<BuiltinHook>) -> ConversionOptions {
+    ConversionOptions {
+      hooks: Hooks::from_config(builtin_hooks),
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn replaces_text_before_parsing() {
+    let options = options_with(vec![BuiltinHook::Replace {
+      find: "{{version}}".to_string(),
+      replace: "1.2".to_string(),
+    }]);
+
+    let html = convert_str("# Release {{version}}\n", Format::Markdown, &options).unwrap();
+    assert!(html.contains("<h1 id=\"release-12\">\nRelease 1.2\n</h1>"));
+  }
+
+  #[test]
+  fn links_ticket_ids() {
+    let options = options_with(vec![BuiltinHook::Autolink {
+      prefix: "JIRA-".to_string(),
+      url: "https://jira.example.com/browse/{id}".to_string(),
+    }]);
+
+    let html = convert_str("Fixed in JIRA-12, not XJIRA-3 or JIRA-\n", Format::Markdown, &options).unwrap();
+    assert!(
+      html.contains("Fixed in <a href=\"https://jira.example.com/browse/JIRA-12\">JIRA-12</a>, not XJIRA-3 or JIRA-\n")
+    );
+  }
+
+  #[test]
+  fn adds_banner_to_html_pages_only() {
+    let banner = BuiltinHook::Banner {
+      html: "<div class=\"banner\">Draft</div>".to_string(),
+    };
+    let html = convert_str("Hello\n", Format::Text, &options_with(vec![banner.clone()])).unwrap();
+    assert!(html.contains("<body>\n\t<div class=\"banner\">Draft</div>\n\t<p>"));
+
+    let options = ConversionOptions {
+      output_format: OutputFormat::Text,
+      ..options_with(vec![banner])
+    };
+    assert_eq!(convert_str("Hello\n", Format::Text, &options).unwrap(), "Hello\n");
+  }
+
+  #[test]
+  fn runs_registered_hooks_at_each_stage() {
+    struct Shout {
+      finalised: Rc<Cell<bool>>,
+    }
+    impl Hook for Shout {
+      fn pre_parse(&self, source: String, page: &PageContext) -> String {
+        assert!(page.path.is_some_and(|path| path.ends_with("page.md")));
+        source.replace("hello", "hi")
+      }
+
+      fn post_parse(&self, document: &mut Document, _page: &PageContext) {
+        for content in document.inlines_mut() {
+          for inline in content {
+            if let Inline::Text(text) = inline {
+              *text = text.to_uppercase();
+            }
+          }
+        }
+      }
+
+      fn post_render(&self, output: String, _page: &PageContext) -> String {
+        output.replace("<p>", "<p class=\"loud\">")
+      }
+
+      fn finalise(&self, output_dir: &Path) -> Result<(), Error> {
+        assert!(output_dir.join("page.html").exists());
+        self.finalised.set(true);
+        Ok(())
+      }
+    }
+
+    let temp_dir = tempfile::tempdir().expect("Create test directory");
+    let input_path = temp_dir.path().join("page.md");
+    fs::write(&input_path, "hello there\n").expect("Create test file");
+    let output_dir = temp_dir.path().join("dist");
+    let finalised = Rc::new(Cell::new(false));
+    let mut settings = BuildSettings {
+      input: input_path.to_string_lossy().to_string(),
+      output_dir_path: output_dir.to_string_lossy().to_string(),
+      template_path: None,
+      shortcodes_dir_path: None,
+      options: ConversionOptions::default(),
+    };
+    settings.options.hooks.register(Shout {
+      finalised: finalised.clone(),
+    });
+
+    build(&settings).expect("Build with hooks");
+    let html = fs::read_to_string(output_dir.join("page.html")).expect("Read test output");
+    assert!(html.contains("<p class=\"loud\">\n\t\tHI THERE\n\n\t</p>"));
+    assert!(finalised.get());
+    assert!(format!("{:?}", settings.options.hooks).contains("Shout"));
+
+    temp_dir.close().expect("Delete test directory");
+  }
+}
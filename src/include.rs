@@ -0,0 +1,289 @@
+use std::path::{Path, PathBuf};
+
+use crate::document::Warning;
+use crate::{error, Error};
+
+const DIRECTIVE_START: &str = "{{";
+const DIRECTIVE_END: &str = "}}";
+const INCLUDE_KEYWORD: &str = "include";
+
+// A file being included, as a canonical path to compare with others and the
+// path it was included with for messages
+struct IncludedFile {
+  canonical_path: PathBuf,
+  path: PathBuf,
+}
+
+/// A page with its include directives replaced by the included files
+#[derive(Debug)]
+pub struct ExpandedSource {
+  pub text: String,
+  /// Canonical paths of every file included, directly or through other
+  /// included files
+  pub included_paths: Vec<PathBuf>,
+  // Where each line of the text came from
+  line_origins: Vec<LineOrigin>,
+}
+
+// The line of the file a line of expanded text is on, and the included file and
+// line it came from if it was included
+#[derive(Debug, Clone)]
+struct LineOrigin {
+  line: usize,
+  included: Option<(PathBuf, usize)>,
+}
+
+impl ExpandedSource {
+  fn new() -> Self {
+    ExpandedSource {
+      text: String::new(),
+      included_paths: Vec::new(),
+      line_origins: vec![LineOrigin {
+        line: 1,
+        included: None,
+      }],
+    }
+  }
+
+  /// Point a warning about a line of the expanded text at the line of the page
+  /// it came from, adding the included file and line if it was included
+  pub fn source_warning(&self, mut warning: Warning) -> Warning {
+    let origin = warning
+      .line
+      .checked_sub(1)
+      .and_then(|index| self.line_origins.get(index));
+    if let Some(origin) = origin {
+      warning.line = origin.line;
+      if let Some((path, line)) = &origin.included {
+        warning.message = format!("{} (line {line} of included file {})", warning.message, path.display());
+      }
+    }
+    warning
+  }
+
+  // Add text from the file being expanded, counting the lines it moves past
+  fn push_text(&mut self, text: &str, line: &mut usize) {
+    for _ in text.matches('\n') {
+      *line += 1;
+      self.line_origins.push(LineOrigin {
+        line: *line,
+        included: None,
+      });
+    }
+    self.text.push_str(text);
+  }
+
+  // Add the expanded text of a file included on a line of the file being
+  // expanded, without the file's last line ending
+  fn push_included(&mut self, included: ExpandedSource, path: &Path, canonical_path: PathBuf, line: usize) {
+    let text = included.text.strip_suffix('\n').unwrap_or(&included.text);
+    let text = text.strip_suffix('\r').unwrap_or(text);
+    let origin = |included_origin: &LineOrigin| LineOrigin {
+      line,
+      included: Some(
+        included_origin
+          .included
+          .clone()
+          .unwrap_or_else(|| (path.to_path_buf(), included_origin.line)),
+      ),
+    };
+
+    // The directive's line is the included file's first line if nothing is
+    // before it
+    if self.text.is_empty() || self.text.ends_with('\n') {
+      if let Some(last_origin) = self.line_origins.last_mut() {
+        *last_origin = origin(&included.line_origins[0]);
+      }
+    }
+    let line_count = text.matches('\n').count();
+    self
+      .line_origins
+      .extend(included.line_origins[1..=line_count].iter().map(origin));
+    self.text.push_str(text);
+    self.included_paths.push(canonical_path);
+    self.included_paths.extend(included.included_paths);
+  }
+}
+
+/// Replace each `{{ include "path" }}` in a page with the contents of the file
+/// at the path, relative to the file the directive is in
+/// Included files can include other files, as long as they don't include a
+/// file they're part of
+/// Without a page path, paths are relative to the current directory
+pub fn expand_includes(source: &str, page_path: Option<&Path>) -> Result<ExpandedSource, Error> {
+  let mut including = Vec::new();
+  if let Some(page_path) = page_path {
+    including.push(IncludedFile {
+      canonical_path: page_path.canonicalize().unwrap_or_else(|_| page_path.to_path_buf()),
+      path: page_path.to_path_buf(),
+    });
+  }
+  expand(source, page_path, &mut including)
+}
+
+fn expand(source: &str, file_path: Option<&Path>, including: &mut Vec<IncludedFile>) -> Result<ExpandedSource, Error> {
+  let dir_path = file_path.and_then(Path::parent).unwrap_or(Path::new(""));
+  let mut expanded = ExpandedSource::new();
+  let mut line = 1;
+  let mut rest = source;
+
+  while let Some(start) = rest.find(DIRECTIVE_START) {
+    let inner_start = start + DIRECTIVE_START.len();
+    let inner_length = match rest[inner_start..].find(DIRECTIVE_END) {
+      Some(inner_length) => inner_length,
+      None => break,
+    };
+    let end = inner_start + inner_length + DIRECTIVE_END.len();
+
+    // Other {{...}} text, like shortcodes, is left as is
+    let Some(include_path) = include_path(&rest[inner_start..(inner_start + inner_length)]) else {
+      expanded.push_text(&rest[..end], &mut line);
+      rest = &rest[end..];
+      continue;
+    };
+
+    expanded.push_text(&rest[..start], &mut line);
+    let path = dir_path.join(include_path);
+    let canonical_path = path
+      .canonicalize()
+      .map_err(|cause| Error::io("read included file", &path, cause))?;
+    if let Some(cycle_start) = including
+      .iter()
+      .position(|included_file| included_file.canonical_path == canonical_path)
+    {
+      let cycle: Vec<String> = including[cycle_start..]
+        .iter()
+        .map(|included_file| included_file.path.display().to_string())
+        .chain([path.display().to_string()])
+        .collect();
+      return Err(Error::Parse {
+        path: file_path.unwrap_or(&path).to_path_buf(),
+        message: format!("it includes a file it's part of: {}", cycle.join(" -> ")),
+      });
+    }
+
+    let contents = error::read_text_file("read included file", &path)?;
+    including.push(IncludedFile {
+      canonical_path: canonical_path.clone(),
+      path: path.clone(),
+    });
+    let included = expand(&contents, Some(&path), including)?;
+    including.pop();
+
+    // The directive's line ending is kept instead of the file's last one
+    expanded.push_included(included, &path, canonical_path, line);
+    line += rest[start..end].matches('\n').count();
+    rest = &rest[end..];
+  }
+
+  expanded.push_text(rest, &mut line);
+  Ok(expanded)
+}
+
+// returns the quoted path in an include directive, or None if it isn't one
+fn include_path(directive: &str) -> Option<&str> {
+  let argument = directive.trim().strip_prefix(INCLUDE_KEYWORD)?;
+  if !argument.starts_with(char::is_whitespace) {
+    return None;
+  }
+  argument.trim().strip_prefix('"')?.strip_suffix('"')
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs;
+
+  use crate::include::*;
+
+  #[test]
+  fn includes_files_relative_to_the_including_file() {
+    let temp_dir = tempfile::tempdir().expect("Create test directory");
+    let snippets_path = temp_dir.path().join("snippets");
+    fs::create_dir(&snippets_path).expect("Create test directory");
+    fs::write(
+      snippets_path.join("license.md"),
+      "Licensed under {{ include \"name.txt\" }}\n",
+    )
+    .expect("Create test file");
+    fs::write(snippets_path.join("name.txt"), "MIT\n").expect("Create test file");
+    let page_path = temp_dir.path().join("page.md");
+
+    let expanded = expand_includes(
+      "# Page\n{{ include \"snippets/license.md\" }}\n{{< youtube id >}}\n",
+      Some(&page_path),
+    )
+    .unwrap();
+    assert_eq!(expanded.text, "# Page\nLicensed under MIT\n{{< youtube id >}}\n");
+    assert_eq!(
+      expanded.included_paths,
+      [
+        snippets_path.join("license.md").canonicalize().unwrap(),
+        snippets_path.join("name.txt").canonicalize().unwrap(),
+      ]
+    );
+
+    temp_dir.close().expect("Delete test directory");
+  }
+
+  #[test]
+  fn reports_include_cycles_and_missing_files() {
+    let temp_dir = tempfile::tempdir().expect("Create test directory");
+    let page_path = temp_dir.path().join("page.md");
+    let other_path = temp_dir.path().join("other.md");
+    fs::write(&page_path, "{{ include \"other.md\" }}").expect("Create test file");
+    fs::write(&other_path, "{{include \"page.md\"}}").expect("Create test file");
+
+    let error = expand_includes("{{ include \"other.md\" }}", Some(&page_path)).unwrap_err();
+    assert!(matches!(&error, Error::Parse { path, .. } if *path == other_path));
+    assert!(error.to_string().ends_with(&format!(
+      "it includes a file it's part of: {} -> {} -> {}",
+      page_path.display(),
+      other_path.display(),
+      page_path.display()
+    )));
+
+    let error = expand_includes("{{ include \"missing.md\" }}", Some(&page_path)).unwrap_err();
+    assert!(matches!(error, Error::Io { .. }));
+
+    temp_dir.close().expect("Delete test directory");
+  }
+
+  #[test]
+  fn points_warnings_at_the_lines_they_came_from() {
+    let temp_dir = tempfile::tempdir().expect("Create test directory");
+    let page_path = temp_dir.path().join("page.md");
+    let snippet_path = temp_dir.path().join("snippet.md");
+    fs::write(&snippet_path, "First\nSecond\n").expect("Create test file");
+    let warning = |line: usize| Warning {
+      line,
+      message: "bad math".to_string(),
+      source: String::new(),
+    };
+
+    let expanded = expand_includes("# Page\n{{ include \"snippet.md\" }}\nLast\n", Some(&page_path)).unwrap();
+    assert_eq!(expanded.text, "# Page\nFirst\nSecond\nLast\n");
+    assert_eq!(expanded.source_warning(warning(1)), warning(1));
+    assert_eq!(
+      expanded.source_warning(warning(3)),
+      Warning {
+        line: 2,
+        message: format!("bad math (line 2 of included file {})", snippet_path.display()),
+        source: String::new(),
+      }
+    );
+    assert_eq!(expanded.source_warning(warning(4)), warning(3));
+
+    temp_dir.close().expect("Delete test directory");
+  }
+
+  #[test]
+  fn only_expands_include_directives() {
+    assert_eq!(include_path(" include \"a.md\" "), Some("a.md"));
+    assert_eq!(include_path("included \"a.md\""), None);
+    assert_eq!(include_path("include a.md"), None);
+    assert_eq!(
+      expand_includes("{{title}} and {{ unclosed", None).unwrap().text,
+      "{{title}} and {{ unclosed"
+    );
+  }
+}
@@ -0,0 +1,962 @@
+//! Convert text and Markdown files into html pages
+//!
+//! Use [`convert_str`] to convert text in memory, or [`build_site`] to convert
+//! every file in a config's input and write the pages to its output directory.
+
+use std::collections::HashMap;
+use std::{fs, path};
+
+pub use config::Config;
+pub use document::{parse, Document};
+pub use error::Error;
+pub use hooks::{Hook, Hooks, PageContext};
+pub use render::{OutputFormat, RenderContext, Renderer};
+
+mod chapters;
+pub mod config;
+pub mod document;
+mod emoji;
+pub mod error;
+mod extensions;
+mod front_matter;
+mod gutenberg;
+pub mod headings;
+pub mod hooks;
+mod include;
+pub mod link_checker;
+mod math;
+mod reflow;
+pub mod render;
+pub mod shortcodes;
+pub mod site;
+mod typography;
+
+/// The kinds of input that can be converted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+  /// Plain text, where the first line can be the title and blank lines
+  /// separate paragraphs
+  Text,
+  Markdown,
+}
+
+impl Format {
+  /// The format matching a file's extension, if it can be converted
+  pub fn from_path(path: &path::Path) -> Option<Self> {
+    match path.extension().and_then(|extension| extension.to_str()) {
+      Some("txt") => Some(Format::Text),
+      Some("md") => Some(Format::Markdown),
+      _ => None,
+    }
+  }
+}
+
+/// Options that affect how each file is converted
+#[derive(Debug)]
+pub struct ConversionOptions {
+  pub lang: String,
+  pub permalinks: bool,
+  pub chapters: bool,
+  pub reflow: bool,
+  pub strip_gutenberg: bool,
+  pub smart_typography: bool,
+  /// Replace `:name:` shortcodes with emoji in markdown files
+  pub emoji: bool,
+  /// Replace emoji shortcodes in text files too, when `emoji` is set
+  pub emoji_in_txt: bool,
+  /// Markdown extensions: `Term` lines followed by `: definition` lines,
+  /// `*[term]: title` abbreviations, `^superscript^` and `~subscript~`
+  pub definition_lists: bool,
+  pub abbreviations: bool,
+  pub superscript: bool,
+  pub subscript: bool,
+  pub output_format: OutputFormat,
+  /// Contents of a custom html template, used instead of the default one
+  pub template: Option<String>,
+  /// Templates for markdown shortcodes, by name
+  pub shortcodes: HashMap<String, String>,
+  pub site: site::SiteMetadata,
+  pub fail_fast: bool,
+  /// Run on each page as it's converted, and once the whole site is written
+  pub hooks: Hooks,
+}
+
+/// Where to read input files from and write html files to, and how to convert
+/// them
+#[derive(Debug)]
+pub struct BuildSettings {
+  pub input: String,
+  pub output_dir_path: String,
+  /// The file the template in the options was read from
+  pub template_path: Option<String>,
+  /// The directory the shortcodes in the options were read from
+  pub shortcodes_dir_path: Option<String>,
+  pub options: ConversionOptions,
+}
+
+/// What happened to each file in the input, so one bad file doesn't stop the
+/// rest of the build
+#[derive(Debug, Default)]
+pub struct BuildReport {
+  pub converted: usize,
+  pub copied: usize,
+  pub skipped: usize,
+  pub warnings: Vec<String>,
+  pub failures: Vec<Error>,
+  /// Number of pages listed in sitemap.xml, if one was written
+  pub sitemap_pages: Option<usize>,
+}
+
+impl Default for ConversionOptions {
+  fn default() -> Self {
+    ConversionOptions {
+      lang: DEFAULT_LANG.to_string(),
+      permalinks: false,
+      chapters: false,
+      reflow: false,
+      strip_gutenberg: false,
+      smart_typography: false,
+      emoji: false,
+      emoji_in_txt: false,
+      definition_lists: false,
+      abbreviations: false,
+      superscript: false,
+      subscript: false,
+      output_format: OutputFormat::Html,
+      template: None,
+      shortcodes: HashMap::new(),
+      site: site::SiteMetadata::default(),
+      fail_fast: false,
+      hooks: Hooks::default(),
+    }
+  }
+}
+
+impl BuildSettings {
+  /// Settings for building the input in a config, reading its template if one
+  /// is set
+  pub fn from_config(config: Config) -> Result<Self, Error> {
+    let template = config
+      .template
+      .as_ref()
+      .map(|template_path| error::read_text_file("read template", path::Path::new(template_path)))
+      .transpose()?;
+    // The default shortcodes directory doesn't have to exist, but one set in
+    // the config does
+    let shortcodes_dir_path = Some(config.shortcodes.as_deref().unwrap_or(DEFAULT_SHORTCODES_DIR))
+      .filter(|dir_path| *dir_path != DEFAULT_SHORTCODES_DIR || path::Path::new(dir_path).is_dir())
+      .map(str::to_string);
+    let shortcodes = match &shortcodes_dir_path {
+      Some(dir_path) => shortcodes::read_templates(path::Path::new(dir_path))?,
+      None => HashMap::new(),
+    };
+
+    Ok(BuildSettings {
+      input: config.input.ok_or_else(Error::missing_input)?,
+      output_dir_path: config.output.unwrap_or_else(|| DEFAULT_OUTPUT_DIR.to_string()),
+      template_path: config.template,
+      shortcodes_dir_path,
+      options: ConversionOptions {
+        lang: config.lang.unwrap_or_else(|| DEFAULT_LANG.to_string()),
+        permalinks: config.permalinks.unwrap_or(false),
+        chapters: config.chapters.unwrap_or(false),
+        reflow: config.reflow.unwrap_or(false),
+        strip_gutenberg: config.strip_gutenberg.unwrap_or(false),
+        smart_typography: config.smart_typography.unwrap_or(false),
+        emoji: config.emoji.unwrap_or(false),
+        emoji_in_txt: config.emoji_in_txt.unwrap_or(false),
+        definition_lists: config.definition_lists.unwrap_or(false),
+        abbreviations: config.abbreviations.unwrap_or(false),
+        superscript: config.superscript.unwrap_or(false),
+        subscript: config.subscript.unwrap_or(false),
+        output_format: config.output_format.unwrap_or_default(),
+        template,
+        shortcodes,
+        fail_fast: config.fail_fast.unwrap_or(false),
+        hooks: Hooks::from_config(config.hooks.unwrap_or_default()),
+        site: site::SiteMetadata {
+          title: config.title,
+          base_url: config.base_url,
+          author: config.author,
+          description: config.description,
+          favicon: config.favicon,
+          copyright: config.copyright,
+          nav: config.nav.unwrap_or_default(),
+          extra: config.extra.unwrap_or_default(),
+        },
+      },
+    })
+  }
+}
+
+pub(crate) const HTML_TEMPLATE: &str = "<!DOCTYPE html>\n<html lang=\"{{lang}}\">\n<head>\n\t<meta \
+                                        charset=\"UTF-8\">\n\t<meta http-equiv=\"X-UA-Compatible\" \
+                                        content=\"IE=edge\">\n\t<meta name=\"viewport\" content=\"width=device-width, \
+                                        initial-scale=1.0\">\n\t<title>\n\t\t{{title}}\n\t</title>\n</head>\n<body>\n";
+pub const DEFAULT_OUTPUT_DIR: &str = "./dist";
+pub const DEFAULT_LANG: &str = "en-CA";
+pub const DEFAULT_SHORTCODES_DIR: &str = "shortcodes";
+// Files in the input directory that are copied to the output as is
+const ASSET_EXTENSIONS: [&str; 9] = ["css", "js", "png", "jpg", "jpeg", "gif", "svg", "ico", "webp"];
+
+/// Convert text or Markdown into a complete page in the output format in the
+/// options, without writing any files
+/// Files in include directives are read relative to the current directory
+pub fn convert_str(input: &str, format: Format, options: &ConversionOptions) -> Result<String, Error> {
+  let page = PageContext {
+    path: None,
+    format,
+    options,
+  };
+  convert_page(input, &page, None).map(|(output, _)| output)
+}
+
+// Include other files in a page's text, then run it through the hooks, parser
+// and renderer
+// returns the page and any warnings from parsing it, with the lines they're on
+// in the page
+fn convert_page(
+  source: &str,
+  page: &PageContext,
+  page_name: Option<&str>,
+) -> Result<(String, Vec<document::Warning>), Error> {
+  let options = page.options;
+  let expanded = include::expand_includes(source, page.path)?;
+  let source = options.hooks.pre_parse(expanded.text.clone(), page);
+  let mut document = parse(&source, page.format, options);
+  options.hooks.post_parse(&mut document, page);
+  let output = options.output_format.renderer().render_document(&RenderContext {
+    document: &document,
+    options,
+    page_name,
+  });
+  let warnings = document
+    .warnings
+    .into_iter()
+    .map(|warning| expanded.source_warning(warning))
+    .collect();
+  Ok((options.hooks.post_render(output, page), warnings))
+}
+
+/// Render a parsed document as a complete page with any renderer
+/// Hooks in the options aren't run
+pub fn render(document: &Document, options: &ConversionOptions, renderer: &dyn Renderer) -> String {
+  renderer.render_document(&RenderContext {
+    document,
+    options,
+    page_name: None,
+  })
+}
+
+/// Render a parsed document as a complete html page
+pub fn render_html(document: &Document, options: &ConversionOptions) -> String {
+  render(document, options, &render::HtmlRenderer)
+}
+
+/// Convert the input in a config and write the pages to its output directory
+pub fn build_site(config: Config) -> Result<BuildReport, Error> { build(&BuildSettings::from_config(config)?) }
+
+/// Convert a file, or the files in a directory, into html pages in the output
+/// directory
+/// Files that fail are listed in the report, unless fail_fast is set
+pub fn build(settings: &BuildSettings) -> Result<BuildReport, Error> {
+  let output_dir_path = &settings.output_dir_path;
+  let options = &settings.options;
+  let path = path::Path::new(&settings.input);
+  let input_error = |message: &str| Error::Input {
+    path: Some(path.to_path_buf()),
+    message: message.to_string(),
+  };
+
+  if !path.exists() {
+    return Err(input_error("No file or directory found"));
+  }
+
+  let mut report = if path.is_dir() {
+    create_output_directory(output_dir_path)?;
+    let dir = fs::read_dir(path).map_err(|cause| Error::io("read input directory", path, cause))?;
+    convert_files_in_directory(dir, output_dir_path, options)?
+  } else if path.is_file() && conversion_file_path_valid(path) {
+    create_output_directory(output_dir_path)?;
+    let warnings = convert_file(path, output_dir_path, options)?;
+    BuildReport {
+      converted: 1,
+      warnings: file_warnings(path, warnings),
+      ..Default::default()
+    }
+  } else {
+    return Err(input_error("Only .txt or .md files are accepted"));
+  };
+
+  report.sitemap_pages = write_sitemap(output_dir_path, options)?;
+  options.hooks.finalise(path::Path::new(output_dir_path))?;
+  Ok(report)
+}
+
+/// List the generated pages in a sitemap, if they're html and the base url
+/// they'll be served from is known
+/// returns the number of pages listed, or None if no sitemap was written
+pub fn write_sitemap(output_dir_path: &str, options: &ConversionOptions) -> Result<Option<usize>, Error> {
+  if options.site.base_url.is_none() || options.output_format != OutputFormat::Html {
+    return Ok(None);
+  }
+
+  site::write_sitemap(path::Path::new(output_dir_path), &options.site)
+    .map(Some)
+    .map_err(|cause| Error::io("write sitemap in", output_dir_path, cause))
+}
+
+fn create_output_directory(output_dir_path: &str) -> Result<(), Error> {
+  // Delete output dir and its contents if it is the default output dir
+  if output_dir_path == DEFAULT_OUTPUT_DIR && path::Path::new(DEFAULT_OUTPUT_DIR).exists() {
+    fs::remove_dir_all(DEFAULT_OUTPUT_DIR)
+      .map_err(|cause| Error::io("delete existing output directory", DEFAULT_OUTPUT_DIR, cause))?;
+  }
+  fs::create_dir_all(output_dir_path).map_err(|cause| Error::io("create output directory", output_dir_path, cause))
+}
+
+// Failures are collected in the report, unless fail_fast is set, in which case
+// the first one is returned
+fn convert_files_in_directory(
+  dir: fs::ReadDir,
+  output_dir_path: &str,
+  options: &ConversionOptions,
+) -> Result<BuildReport, Error> {
+  let mut report = BuildReport::default();
+
+  // Iterate over each file in directory, calling the convert file function
+  // Assets like stylesheets and images are copied instead
+  for entry in dir {
+    let result = entry
+      .map_err(|cause| Error::Io {
+        path: None,
+        action: "read input directory".to_string(),
+        cause,
+      })
+      .and_then(|entry| {
+        let path = entry.path();
+        if asset_file_path_valid(&path) && path.is_file() {
+          copy_asset(&path, output_dir_path).map(|()| report.copied += 1)
+        } else if conversion_file_path_valid(&path) && path.is_file() {
+          convert_file(&path, output_dir_path, options).map(|warnings| {
+            report.converted += 1;
+            report.warnings.extend(file_warnings(&path, warnings));
+          })
+        } else {
+          report.skipped += 1;
+          report.warnings.push(format!(
+            "Skipped '{}': only .txt and .md files are converted",
+            path.display()
+          ));
+          Ok(())
+        }
+      });
+
+    match result {
+      Err(error) if options.fail_fast => return Err(error),
+      Err(error) => report.failures.push(error),
+      Ok(()) => {}
+    }
+  }
+
+  Ok(report)
+}
+
+/// Copy a stylesheet, script or image to the output directory as is
+pub fn copy_asset(path: &path::Path, output_dir_path: &str) -> Result<(), Error> {
+  let output_path = path::Path::new(output_dir_path).join(path.file_name().unwrap_or_default());
+  fs::copy(path, output_path).map_err(|cause| Error::io("copy asset file", path, cause))?;
+  Ok(())
+}
+
+/// Convert a .txt or .md file into an html page in the output directory,
+/// replacing any page already there
+/// returns warnings about problems in the file that didn't stop it being
+/// converted
+pub fn convert_file(
+  path: &path::Path,
+  output_dir_path: &str,
+  options: &ConversionOptions,
+) -> Result<Vec<document::Warning>, Error> {
+  // We only want to convert .txt and .md files
+  let format = match Format::from_path(path) {
+    Some(format) => format,
+    None => return Ok(Vec::new()),
+  };
+
+  let contents = error::read_text_file("read", path)?;
+  // The output file is named after the input file
+  let page_name = path.file_stem().unwrap_or_default().to_string_lossy();
+  let (page, warnings) = convert_page(
+    &contents,
+    &PageContext {
+      path: Some(path),
+      format,
+      options,
+    },
+    Some(&page_name),
+  )?;
+
+  let output_path = output_file_path(path, output_dir_path, options.output_format);
+  fs::write(&output_path, page).map_err(|cause| Error::io("write output file", output_path, cause))?;
+  Ok(warnings)
+}
+
+/// The files a page includes, directly or through other included files, as
+/// canonical paths
+pub fn included_files(path: &path::Path) -> Result<Vec<path::PathBuf>, Error> {
+  let contents = error::read_text_file("read", path)?;
+  include::expand_includes(&contents, Some(path)).map(|expanded| expanded.included_paths)
+}
+
+/// Warnings for a file, described with its path for the build report
+pub fn file_warnings(path: &path::Path, warnings: Vec<document::Warning>) -> Vec<String> {
+  warnings
+    .into_iter()
+    .map(|warning| format!("{}, {warning}", path.display()))
+    .collect()
+}
+
+pub(crate) fn escape_html_attribute(value: &str) -> String {
+  value
+    .replace('&', "&amp;")
+    .replace('"', "&quot;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+}
+
+/// Whether a file is converted into an html page
+pub fn conversion_file_path_valid(path: &path::Path) -> bool { Format::from_path(path).is_some() }
+
+/// Whether a file is copied to the output directory as is
+pub fn asset_file_path_valid(path: &path::Path) -> bool {
+  let extension = path
+    .extension()
+    .and_then(|extension| extension.to_str())
+    .unwrap_or_default();
+  ASSET_EXTENSIONS.contains(&extension.to_lowercase().as_str())
+}
+
+/// The file generated for an input file e.g. dist/page.html for page.md
+pub fn output_file_path(path: &path::Path, output_dir_path: &str, output_format: OutputFormat) -> path::PathBuf {
+  let file_name = path.file_stem().unwrap_or_default().to_string_lossy();
+  path::PathBuf::from(output_dir_path).join(format!("{file_name}.{}", output_format.extension()))
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs::File;
+  use std::io::{Read, Write};
+
+  use crate::*;
+
+  fn test_options() -> ConversionOptions {
+    ConversionOptions {
+      lang: "en".to_string(),
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn converts_txt_files() {
+    let input_file_path = path::Path::new("sample.txt");
+    assert!(conversion_file_path_valid(input_file_path));
+  }
+
+  #[test]
+  fn converts_md_files() {
+    let input_file_path = path::Path::new("sample.md");
+    assert!(conversion_file_path_valid(input_file_path));
+  }
+
+  #[test]
+  fn does_not_convert_unsupported_file_types() {
+    let input_file_path = path::Path::new("sample.exe");
+    assert!(!conversion_file_path_valid(input_file_path));
+  }
+
+  #[test]
+  fn does_not_convert_files_without_extension() {
+    let input_file_path = path::Path::new("4913");
+    assert!(!conversion_file_path_valid(input_file_path));
+  }
+
+  #[test]
+  fn creates_output_directory() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let output_dir_path = temp_dir.path().join("test_dir");
+    let output_dir_path_string = output_dir_path.as_os_str().to_str().unwrap().to_string();
+
+    create_output_directory(&output_dir_path_string).unwrap();
+    assert!(output_dir_path.is_dir());
+
+    temp_dir.close().expect("Delete test directory");
+  }
+
+  #[test]
+  fn recreates_default_output_directory() {
+    let output_dir_path = path::Path::new(&DEFAULT_OUTPUT_DIR);
+    fs::create_dir_all(output_dir_path).expect("Create test default output dir");
+    File::create(output_dir_path.join("test_file.txt")).expect("Create test file");
+
+    create_output_directory(DEFAULT_OUTPUT_DIR).unwrap();
+
+    assert!(output_dir_path.is_dir());
+    assert!(output_dir_path.read_dir().unwrap().next().is_none());
+
+    fs::remove_dir_all(output_dir_path).expect("Delete test directory");
+  }
+
+  #[test]
+  fn retains_non_default_output_directory_contents() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let output_dir_path = temp_dir.path().join("test_dir");
+    let output_dir_path_string = output_dir_path.as_os_str().to_str().unwrap().to_string();
+    let existing_file_path = output_dir_path.join("test_file.txt");
+    fs::create_dir(&output_dir_path).expect("Create test output dir");
+    File::create(&existing_file_path).expect("Create test file");
+
+    create_output_directory(&output_dir_path_string).unwrap();
+
+    assert!(output_dir_path.is_dir());
+    assert_eq!(
+      output_dir_path.read_dir().unwrap().next().unwrap().expect("").path(),
+      existing_file_path
+    )
+  }
+
+  #[test]
+  fn creates_html_file() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let test_input_path = temp_dir.path().join("html_template_test.txt");
+    let mut test_input_file = File::create(&test_input_path).unwrap();
+    writeln!(test_input_file, "test").expect("Create test input file");
+
+    convert_file(
+      test_input_path.as_path(),
+      &temp_dir.path().to_path_buf().into_os_string().into_string().unwrap(),
+      &test_options(),
+    )
+    .unwrap();
+
+    let expected_output = HTML_TEMPLATE
+      .replace("{{title}}", "html_template_test")
+      .replace("{{lang}}", "en")
+      + "\t<p>\n\t\ttest\n\n\t</p>\n</body>\n</html>\n";
+    let test_output_file_path = temp_dir.path().join("html_template_test.html");
+    let mut test_output_file = File::open(&test_output_file_path).unwrap();
+    let mut converted_string = String::new();
+    test_output_file
+      .read_to_string(&mut converted_string)
+      .expect("Read test output file");
+
+    assert_eq!(converted_string, expected_output);
+
+    drop(test_input_file);
+    temp_dir.close().expect("Delete test directory");
+  }
+
+  #[test]
+  fn creates_html_in_directory() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let test_input_path1 = temp_dir.path().join("html_template_test1.txt");
+    let test_input_path2 = temp_dir.path().join("html_template_test2.txt");
+    let mut test_input_file1 = File::create(&test_input_path1).unwrap();
+    let mut test_input_file2 = File::create(&test_input_path2).unwrap();
+    writeln!(test_input_file1, "test1").expect("Create test input file");
+    writeln!(test_input_file2, "test2").expect("Create test input file");
+
+    let out_dir = "./out".to_owned();
+    fs::create_dir_all(&out_dir).expect("Create test output directory");
+
+    let input_dir = fs::read_dir(&temp_dir).expect("Read input directory");
+    convert_files_in_directory(input_dir, &out_dir, &test_options()).unwrap();
+
+    let expected_output1 = HTML_TEMPLATE
+      .replace("{{title}}", "html_template_test1")
+      .replace("{{lang}}", "en")
+      + "\t<p>\n\t\ttest1\n\n\t</p>\n</body>\n</html>\n";
+    let expected_output2 = HTML_TEMPLATE
+      .replace("{{title}}", "html_template_test2")
+      .replace("{{lang}}", "en")
+      + "\t<p>\n\t\ttest2\n\n\t</p>\n</body>\n</html>\n";
+
+    let output_file1 = "./out/html_template_test1.html";
+    let output_file2 = "./out/html_template_test2.html";
+    let mut test_output_file1 = File::open(output_file1).unwrap();
+    let mut test_output_file2 = File::open(output_file2).unwrap();
+    let mut converted_string1 = String::new();
+    let mut converted_string2 = String::new();
+    test_output_file1
+      .read_to_string(&mut converted_string1)
+      .expect("Read test output file");
+    test_output_file2
+      .read_to_string(&mut converted_string2)
+      .expect("Read test output file");
+
+    assert_eq!(converted_string1, expected_output1);
+    assert_eq!(converted_string2, expected_output2);
+
+    fs::remove_dir_all(&out_dir).expect("Remove output directory");
+    drop(test_input_file1);
+    drop(test_input_file2);
+    temp_dir.close().expect("Delete test directory");
+  }
+
+  #[test]
+  fn adds_ids_and_toc_to_markdown_headings() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let test_input_path = temp_dir.path().join("toc_test.md");
+    fs::write(&test_input_path, "[[toc]]\n# Intro\n## Usage\n# Intro\n").expect("Create test input file");
+
+    convert_file(
+      test_input_path.as_path(),
+      &temp_dir.path().to_path_buf().into_os_string().into_string().unwrap(),
+      &test_options(),
+    )
+    .unwrap();
+
+    let converted_string = fs::read_to_string(temp_dir.path().join("toc_test.html")).expect("Read test output file");
+    let expected_toc = "<nav class=\"toc\">\n<ul>\n<li><a href=\"#intro\">Intro</a><ul>\n<li><a \
+                        href=\"#usage\">Usage</a></li>\n</ul>\n</li>\n<li><a \
+                        href=\"#intro-1\">Intro</a></li>\n</ul>\n</nav>\n";
+
+    assert!(converted_string.contains(expected_toc));
+    assert!(converted_string.contains("<h1 id=\"intro\">\nIntro\n</h1>"));
+    assert!(converted_string.contains("<h2 id=\"usage\">\nUsage\n</h2>"));
+    assert!(converted_string.contains("<h1 id=\"intro-1\">\nIntro\n</h1>"));
+
+    temp_dir.close().expect("Delete test directory");
+  }
+
+  #[test]
+  fn adds_toc_and_permalinks_from_front_matter() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let test_input_path = temp_dir.path().join("front_matter_test.md");
+    fs::write(&test_input_path, "---\ntoc: true\n---\n# Only heading\n").expect("Create test input file");
+    let options = ConversionOptions {
+      permalinks: true,
+      ..test_options()
+    };
+
+    convert_file(
+      test_input_path.as_path(),
+      &temp_dir.path().to_path_buf().into_os_string().into_string().unwrap(),
+      &options,
+    )
+    .unwrap();
+
+    let converted_string =
+      fs::read_to_string(temp_dir.path().join("front_matter_test.html")).expect("Read test output file");
+    let expected_body = "<body>\n<nav class=\"toc\">\n<ul>\n<li><a href=\"#only-heading\">Only \
+                         heading</a></li>\n</ul>\n</nav>\n\t<p>\n<h1 id=\"only-heading\">\nOnly heading <a \
+                         class=\"anchor\" href=\"#only-heading\" aria-label=\"Permalink\">#</a>\n</h1>\n";
+
+    assert!(converted_string.contains(expected_body));
+
+    temp_dir.close().expect("Delete test directory");
+  }
+
+  #[test]
+  fn adds_chapter_headings_and_toc_to_txt_files() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let test_input_path = temp_dir.path().join("chapters_test.txt");
+    fs::write(&test_input_path, "I.\n\nFirst line\nII.\n\nCHAPTER III\n\nLast line\n").expect("Create test input file");
+    let options = ConversionOptions {
+      chapters: true,
+      ..test_options()
+    };
+
+    convert_file(
+      test_input_path.as_path(),
+      &temp_dir.path().to_path_buf().into_os_string().into_string().unwrap(),
+      &options,
+    )
+    .unwrap();
+
+    let converted_string =
+      fs::read_to_string(temp_dir.path().join("chapters_test.html")).expect("Read test output file");
+    let expected_body = "<body>\n<nav class=\"toc\">\n<ul>\n<li><a href=\"#i\">I.</a></li>\n<li><a \
+                         href=\"#chapter-iii\">CHAPTER III</a></li>\n</ul>\n</nav>\n\t<p>\n\t</p>\n\t<h2 \
+                         id=\"i\">\n\t\tI.\n\t</h2>\n\t<p>\n\t</p>\n\t<p>\n\t\tFirst line\n\t\tII.\n";
+
+    assert!(converted_string.contains(expected_body));
+    assert!(converted_string.contains("\t<h2 id=\"chapter-iii\">\n\t\tCHAPTER III\n\t</h2>\n"));
+
+    temp_dir.close().expect("Delete test directory");
+  }
+
+  #[test]
+  fn reflows_txt_paragraphs() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let test_input_path = temp_dir.path().join("reflow_test.txt");
+    fs::write(
+      &test_input_path,
+      "It was a dark and stormy night; the rain fell in\ntorrents, except at occasional intervals.\n\nThe end.\n",
+    )
+    .expect("Create test input file");
+    let options = ConversionOptions {
+      reflow: true,
+      ..test_options()
+    };
+
+    convert_file(
+      test_input_path.as_path(),
+      &temp_dir.path().to_path_buf().into_os_string().into_string().unwrap(),
+      &options,
+    )
+    .unwrap();
+
+    let converted_string = fs::read_to_string(temp_dir.path().join("reflow_test.html")).expect("Read test output file");
+    let expected_body = "<body>\n\t<p>\n\t\tIt was a dark and stormy night; the rain fell in torrents, except at \
+                         occasional intervals.\n\t</p>\n\t<p>\n\t\tThe end.\n\n\t</p>\n</body>";
+
+    assert!(converted_string.contains(expected_body));
+
+    temp_dir.close().expect("Delete test directory");
+  }
+
+  #[test]
+  fn strips_gutenberg_boilerplate_from_txt_files() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let test_input_path = temp_dir.path().join("gutenberg_test.txt");
+    fs::write(
+      &test_input_path,
+      "Title: Silver Blaze\nAuthor: Arthur Conan Doyle\n*** START OF THE PROJECT GUTENBERG EBOOK SILVER BLAZE \
+       ***\n\nText\n\n*** END OF THE PROJECT GUTENBERG EBOOK SILVER BLAZE ***\nLicence\n",
+    )
+    .expect("Create test input file");
+    let options = ConversionOptions {
+      strip_gutenberg: true,
+      ..test_options()
+    };
+
+    convert_file(
+      test_input_path.as_path(),
+      &temp_dir.path().to_path_buf().into_os_string().into_string().unwrap(),
+      &options,
+    )
+    .unwrap();
+
+    let expected_output = HTML_TEMPLATE
+      .replace("{{title}}", "Silver Blaze\n")
+      .replace("{{lang}}", "en")
+      .replace(
+        "</head>",
+        "\t<meta name=\"author\" content=\"Arthur Conan Doyle\">\n</head>",
+      )
+      + "\t<h1 id=\"silver-blaze\">\n\t\tSilver Blaze\n\t</h1>\n\t<p>\n\t\tText\n\n\t</p>\n</body>\n</html>\n";
+    let converted_string =
+      fs::read_to_string(temp_dir.path().join("gutenberg_test.html")).expect("Read test output file");
+
+    assert_eq!(converted_string, expected_output);
+
+    temp_dir.close().expect("Delete test directory");
+  }
+
+  #[test]
+  fn applies_smart_typography_unless_disabled_in_front_matter() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let output_dir_path_string = temp_dir.path().to_path_buf().into_os_string().into_string().unwrap();
+    let smart_input_path = temp_dir.path().join("smart_test.md");
+    let plain_input_path = temp_dir.path().join("plain_test.md");
+    fs::write(&smart_input_path, "\"Quoted\" -- [link](a--b.html)\n").expect("Create test input file");
+    fs::write(&plain_input_path, "---\nsmart_typography: false\n---\n\"Quoted\"\n").expect("Create test input file");
+    let options = ConversionOptions {
+      smart_typography: true,
+      ..test_options()
+    };
+
+    for input_path in [&smart_input_path, &plain_input_path] {
+      convert_file(input_path, &output_dir_path_string, &options).unwrap();
+    }
+
+    let smart_output = fs::read_to_string(temp_dir.path().join("smart_test.html")).expect("Read test output file");
+    let plain_output = fs::read_to_string(temp_dir.path().join("plain_test.html")).expect("Read test output file");
+
+    assert!(smart_output.contains("\t\t“Quoted” – <a href=\"a--b.html\">link</a>\n"));
+    assert!(plain_output.contains("\t\t\"Quoted\"\n"));
+
+    temp_dir.close().expect("Delete test directory");
+  }
+
+  #[test]
+  fn replaces_emoji_shortcodes_in_markdown_unless_asked_for_text_too() {
+    let mut options = ConversionOptions {
+      emoji: true,
+      ..test_options()
+    };
+    let markdown = convert_str("Launch :rocket: `:rocket:`\n", Format::Markdown, &options).unwrap();
+    assert!(markdown.contains("\t\tLaunch 🚀 `:rocket:`\n"));
+    let text = convert_str("Launch :rocket:\n", Format::Text, &options).unwrap();
+    assert!(text.contains("\t\tLaunch :rocket:\n"));
+
+    options.emoji_in_txt = true;
+    let text = convert_str("Launch :rocket:\n", Format::Text, &options).unwrap();
+    assert!(text.contains("\t\tLaunch 🚀\n"));
+  }
+
+  #[test]
+  fn replaces_existing_output_file() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let test_input_path = temp_dir.path().join("rebuild_test.txt");
+    let output_dir_path_string = temp_dir.path().to_path_buf().into_os_string().into_string().unwrap();
+
+    fs::write(&test_input_path, "first\n").expect("Create test input file");
+    convert_file(&test_input_path, &output_dir_path_string, &test_options()).unwrap();
+    fs::write(&test_input_path, "second\n").expect("Update test input file");
+    convert_file(&test_input_path, &output_dir_path_string, &test_options()).unwrap();
+
+    let converted_string =
+      fs::read_to_string(temp_dir.path().join("rebuild_test.html")).expect("Read test output file");
+
+    assert!(!converted_string.contains("first"));
+    assert_eq!(converted_string.matches("<html").count(), 1);
+
+    temp_dir.close().expect("Delete test directory");
+  }
+
+  #[test]
+  fn uses_custom_template() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let test_input_path = temp_dir.path().join("template_test.txt");
+    fs::write(&test_input_path, "Title\n\n\ntest\n").expect("Create test input file");
+    let options = ConversionOptions {
+      template: Some("<html lang=\"{{lang}}\"><title>{{title}}</title>\n{{content}}</html>\n".to_string()),
+      ..test_options()
+    };
+
+    convert_file(
+      test_input_path.as_path(),
+      &temp_dir.path().to_path_buf().into_os_string().into_string().unwrap(),
+      &options,
+    )
+    .unwrap();
+
+    let converted_string =
+      fs::read_to_string(temp_dir.path().join("template_test.html")).expect("Read test output file");
+    let expected_output = "<html lang=\"en\"><title>Title</title>\n\t<h1 \
+                           id=\"title\">\n\t\tTitle\n\t</h1>\n\t<p>\n\t\ttest\n\n\t</p>\n</html>\n";
+
+    assert_eq!(converted_string, expected_output);
+
+    temp_dir.close().expect("Delete test directory");
+  }
+
+  #[test]
+  fn copies_assets_in_directory() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let input_dir_path = temp_dir.path().join("in");
+    let output_dir_path = temp_dir.path().join("out");
+    fs::create_dir_all(&input_dir_path).expect("Create test input directory");
+    fs::create_dir_all(&output_dir_path).expect("Create test output directory");
+    fs::write(input_dir_path.join("styles.css"), "body {}").expect("Create test input file");
+    fs::write(input_dir_path.join("notes.json"), "{}").expect("Create test input file");
+
+    convert_files_in_directory(
+      fs::read_dir(&input_dir_path).unwrap(),
+      output_dir_path.to_str().unwrap(),
+      &test_options(),
+    )
+    .unwrap();
+
+    assert_eq!(
+      fs::read_to_string(output_dir_path.join("styles.css")).unwrap(),
+      "body {}"
+    );
+    assert!(!output_dir_path.join("notes.json").exists());
+
+    temp_dir.close().expect("Delete test directory");
+  }
+
+  #[test]
+  fn reports_input_errors() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let output_dir_path = temp_dir.path().join("out").to_str().unwrap().to_string();
+    let notes_path = temp_dir.path().join("notes.json");
+    fs::write(&notes_path, "{}").expect("Create test input file");
+
+    let settings = |input: &str| BuildSettings {
+      input: input.to_string(),
+      output_dir_path: output_dir_path.clone(),
+      template_path: None,
+      shortcodes_dir_path: None,
+      options: test_options(),
+    };
+
+    let missing_error = build(&settings("./missing")).unwrap_err();
+    let unsupported_error = build(&settings(notes_path.to_str().unwrap())).unwrap_err();
+
+    assert!(matches!(missing_error, Error::Input { .. }));
+    assert!(matches!(unsupported_error, Error::Input { .. }));
+    assert_eq!(missing_error.exit_code(), 66);
+    assert!(!path::Path::new(&output_dir_path).exists());
+
+    temp_dir.close().expect("Delete test directory");
+  }
+
+  #[test]
+  fn reports_missing_template() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let test_input_path = temp_dir.path().join("page.txt");
+    fs::write(&test_input_path, "Some text\n").expect("Create test input file");
+    let config = Config {
+      input: test_input_path.to_str().map(str::to_string),
+      output: temp_dir.path().to_str().map(str::to_string),
+      template: temp_dir.path().join("missing.html").to_str().map(str::to_string),
+      ..Default::default()
+    };
+
+    let error = build_site(config).unwrap_err();
+
+    assert!(error.to_string().starts_with("Could not read template"));
+    assert!(!temp_dir.path().join("page.html").exists());
+
+    temp_dir.close().expect("Delete test directory");
+  }
+
+  #[test]
+  fn converts_strings_without_files() {
+    let options = ConversionOptions {
+      template: Some("<title>{{title}}</title>\n{{content}}".to_string()),
+      ..test_options()
+    };
+
+    assert_eq!(
+      convert_str("# Notes\nSee [docs](docs.html)\n", Format::Markdown, &options).unwrap(),
+      "<title></title>\n\t<p>\n<h1 id=\"notes\">\nNotes\n</h1>\n\t\tSee <a href=\"docs.html\">docs</a>\n\n\t</p>\n"
+    );
+    assert_eq!(
+      convert_str("Title\n\n\nSome text\n", Format::Text, &options).unwrap(),
+      "<title>Title</title>\n\t<h1 id=\"title\">\n\t\tTitle\n\t</h1>\n\t<p>\n\t\tSome text\n\n\t</p>\n"
+    );
+  }
+
+  #[test]
+  fn continues_after_files_that_fail() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let input_dir_path = temp_dir.path().join("input");
+    let output_dir_path = temp_dir.path().join("out").to_str().unwrap().to_string();
+    fs::create_dir_all(&input_dir_path).expect("Create test input directory");
+    fs::create_dir_all(&output_dir_path).expect("Create test output directory");
+    fs::write(input_dir_path.join("binary.txt"), [0xff, 0xfe]).expect("Create test input file");
+    fs::write(input_dir_path.join("good.md"), "# Good\n").expect("Create test input file");
+    fs::write(input_dir_path.join("notes.json"), "{}").expect("Create test input file");
+
+    let read_input_dir = || fs::read_dir(&input_dir_path).expect("Read input directory");
+    let report = convert_files_in_directory(read_input_dir(), &output_dir_path, &test_options()).unwrap();
+
+    assert_eq!((report.converted, report.skipped, report.failures.len()), (1, 1, 1));
+    assert!(matches!(report.failures[0], Error::Parse { .. }));
+    assert!(path::Path::new(&output_dir_path).join("good.html").exists());
+
+    let options = ConversionOptions {
+      fail_fast: true,
+      ..test_options()
+    };
+    assert!(convert_files_in_directory(read_input_dir(), &output_dir_path, &options).is_err());
+
+    temp_dir.close().expect("Delete test directory");
+  }
+}
@@ -0,0 +1,375 @@
+use std::collections::HashMap;
+use std::{fmt, fs, path};
+
+// Attributes whose values are followed when checking links
+const LINK_ATTRIBUTES: [&str; 2] = ["href", "src"];
+// Attributes that can be the target of a #fragment
+const ANCHOR_ATTRIBUTES: [&str; 2] = ["id", "name"];
+
+/// A relative link in a generated page whose target does not exist in the
+/// output directory
+#[derive(Debug, PartialEq, Eq)]
+pub struct BrokenLink {
+  pub file: path::PathBuf,
+  pub line: usize,
+  pub target: String,
+}
+
+impl fmt::Display for BrokenLink {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    write!(
+      formatter,
+      "{}:{}: broken link to '{}'",
+      self.file.display(),
+      self.line,
+      self.target
+    )
+  }
+}
+
+/// Scan every html file in the output directory for relative href/src targets
+/// and #fragments, returning the ones that can't be resolved
+pub fn check_links(output_dir: &path::Path) -> Vec<BrokenLink> {
+  let mut pages = Vec::new();
+  collect_html_files(output_dir, &mut pages);
+  pages.sort();
+
+  let output_root = normalize_path(output_dir);
+  let mut anchors_by_page: HashMap<path::PathBuf, Vec<String>> = HashMap::new();
+  let mut broken_links = Vec::new();
+
+  for page in &pages {
+    let contents = match fs::read_to_string(page) {
+      Ok(contents) => contents,
+      Err(_) => continue,
+    };
+
+    for (line_index, line) in contents.lines().enumerate() {
+      for target in find_attribute_values(line, &LINK_ATTRIBUTES) {
+        if !link_target_exists(&output_root, page, &target, &mut anchors_by_page) {
+          broken_links.push(BrokenLink {
+            file: page.clone(),
+            line: line_index + 1,
+            target,
+          });
+        }
+      }
+    }
+  }
+
+  broken_links
+}
+
+fn collect_html_files(dir: &path::Path, pages: &mut Vec<path::PathBuf>) {
+  let entries = match fs::read_dir(dir) {
+    Ok(entries) => entries,
+    Err(_) => return,
+  };
+
+  for entry in entries.flatten() {
+    let path = entry.path();
+    if path.is_dir() {
+      collect_html_files(&path, pages);
+    } else if path.extension().is_some_and(|extension| extension == "html") {
+      pages.push(path);
+    }
+  }
+}
+
+// Returns the values of the given attributes found in a line of html, with
+// character references like `&amp;` decoded
+fn find_attribute_values(line: &str, attributes: &[&str]) -> Vec<String> {
+  let mut values = Vec::new();
+  let line_bytes = line.as_bytes();
+
+  for attribute in attributes {
+    let pattern = format!("{attribute}=");
+    let mut search_start = 0;
+
+    while let Some(offset) = line[search_start..].find(&pattern) {
+      let attribute_start = search_start + offset;
+      let value_start = attribute_start + pattern.len();
+      search_start = value_start;
+
+      // Skip matches that are part of a longer attribute name e.g. data-id=
+      if attribute_start > 0 && !line_bytes[attribute_start - 1].is_ascii_whitespace() {
+        continue;
+      }
+
+      let quote = match line_bytes.get(value_start) {
+        Some(&quote) if quote == b'"' || quote == b'\'' => quote as char,
+        _ => continue,
+      };
+
+      if let Some(value_length) = line[(value_start + 1)..].find(quote) {
+        values.push(decode_html_entities(
+          &line[(value_start + 1)..(value_start + 1 + value_length)],
+        ));
+        search_start = value_start + 1 + value_length;
+      }
+    }
+  }
+
+  values
+}
+
+fn link_target_exists(
+  output_root: &path::Path,
+  page: &path::Path,
+  target: &str,
+  anchors_by_page: &mut HashMap<path::PathBuf, Vec<String>>,
+) -> bool {
+  if is_external_link(target) {
+    return true;
+  }
+
+  let (target_path, fragment) = match target.split_once('#') {
+    Some((target_path, fragment)) => (target_path, fragment),
+    None => (target, ""),
+  };
+  let target_path = target_path.split('?').next().unwrap_or_default();
+
+  // Links with an empty path point back to the current page
+  let mut resolved_path = if target_path.is_empty() {
+    normalize_path(page)
+  } else if let Some(root_relative) = target_path.strip_prefix('/') {
+    normalize_path(&output_root.join(percent_decode(root_relative)))
+  } else {
+    normalize_path(&page.parent().unwrap_or(output_root).join(percent_decode(target_path)))
+  };
+
+  if !resolved_path.starts_with(output_root) {
+    return false;
+  }
+
+  if resolved_path.is_dir() {
+    resolved_path = resolved_path.join("index.html");
+  }
+
+  if !resolved_path.is_file() {
+    return false;
+  }
+
+  let fragment = percent_decode(fragment);
+  if fragment.is_empty() || fragment.eq_ignore_ascii_case("top") {
+    return true;
+  }
+
+  if resolved_path.extension().is_none_or(|extension| extension != "html") {
+    return true;
+  }
+
+  anchors_by_page
+    .entry(resolved_path.clone())
+    .or_insert_with(|| {
+      fs::read_to_string(&resolved_path)
+        .unwrap_or_default()
+        .lines()
+        .flat_map(|line| find_attribute_values(line, &ANCHOR_ATTRIBUTES))
+        .collect()
+    })
+    .contains(&fragment)
+}
+
+// Links with a scheme (https:, mailto:, data:...) or protocol relative links
+// can't be checked offline
+fn is_external_link(target: &str) -> bool {
+  if target.starts_with("//") {
+    return true;
+  }
+
+  match target.find(':') {
+    Some(colon) => {
+      let scheme = &target[..colon];
+      !scheme.is_empty()
+        && scheme
+          .chars()
+          .all(|char| char.is_ascii_alphanumeric() || char == '+' || char == '-' || char == '.')
+    }
+    None => false,
+  }
+}
+
+// Resolve `.` and `..` components without touching the filesystem
+fn normalize_path(path: &path::Path) -> path::PathBuf {
+  let mut normalized = path::PathBuf::new();
+
+  for component in path.components() {
+    match component {
+      path::Component::CurDir => {}
+      path::Component::ParentDir => {
+        if !normalized.pop() {
+          normalized.push("..");
+        }
+      }
+      _ => normalized.push(component),
+    }
+  }
+
+  normalized
+}
+
+// Named and numeric character references e.g. `&amp;` or `&#38;`, leaving
+// unknown ones as they are
+fn decode_html_entities(text: &str) -> String {
+  let mut decoded = String::with_capacity(text.len());
+  let mut rest = text;
+
+  while let Some(start) = rest.find('&') {
+    decoded.push_str(&rest[..start]);
+    rest = &rest[start..];
+    let reference = rest[1..].split_once(';').map(|(reference, _)| reference);
+    let char = reference.and_then(|reference| match reference {
+      "amp" => Some('&'),
+      "lt" => Some('<'),
+      "gt" => Some('>'),
+      "quot" => Some('"'),
+      "apos" => Some('\''),
+      _ => {
+        let number = reference.strip_prefix('#')?;
+        let code = match number.strip_prefix(['x', 'X']) {
+          Some(hex_digits) => u32::from_str_radix(hex_digits, 16),
+          None => number.parse(),
+        };
+        code.ok().and_then(char::from_u32)
+      }
+    });
+
+    match (char, reference) {
+      (Some(char), Some(reference)) => {
+        decoded.push(char);
+        rest = &rest[(reference.len() + 2)..];
+      }
+      _ => {
+        decoded.push('&');
+        rest = &rest[1..];
+      }
+    }
+  }
+
+  decoded.push_str(rest);
+  decoded
+}
+
+// `%` followed by two hex digits is the byte they stand for, and any other `%`
+// is kept as is
+pub fn percent_decode(text: &str) -> String {
+  let text_bytes = text.as_bytes();
+  let mut decoded = Vec::with_capacity(text_bytes.len());
+  let mut i = 0;
+
+  while i < text_bytes.len() {
+    let hex_digits = text_bytes.get((i + 1)..(i + 3)).unwrap_or_default();
+    if text_bytes[i] == b'%' && hex_digits.len() == 2 && hex_digits.iter().all(u8::is_ascii_hexdigit) {
+      let hex_digits = std::str::from_utf8(hex_digits).unwrap_or_default();
+      if let Ok(byte) = u8::from_str_radix(hex_digits, 16) {
+        decoded.push(byte);
+        i += 3;
+        continue;
+      }
+    }
+    decoded.push(text_bytes[i]);
+    i += 1;
+  }
+
+  String::from_utf8_lossy(&decoded).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs;
+
+  use crate::link_checker::*;
+
+  fn write_page(dir: &path::Path, name: &str, contents: &str) {
+    let page_path = dir.join(name);
+    fs::create_dir_all(page_path.parent().unwrap()).expect("Create test page directory");
+    fs::write(page_path, contents).expect("Create test page");
+  }
+
+  #[test]
+  fn finds_href_and_src_values() {
+    let line = "<a href=\"one.html\"><img src='two.png' data-href=\"three.html\"></a>";
+    assert_eq!(find_attribute_values(line, &LINK_ATTRIBUTES), ["one.html", "two.png"]);
+  }
+
+  #[test]
+  fn ignores_external_links() {
+    assert!(is_external_link("https://www.example.com"));
+    assert!(is_external_link("mailto:someone@example.com"));
+    assert!(is_external_link("//cdn.example.com/style.css"));
+    assert!(!is_external_link("www.example.com"));
+    assert!(!is_external_link("./page.html#section"));
+  }
+
+  #[test]
+  fn decodes_percent_encoded_paths() {
+    assert_eq!(percent_decode("Silver%20Blaze.html"), "Silver Blaze.html");
+    assert_eq!(percent_decode("100%"), "100%");
+    assert_eq!(percent_decode("a%+5b%2"), "a%+5b%2");
+    assert_eq!(percent_decode("%E2%9C%93%2f"), "\u{2713}/");
+  }
+
+  #[test]
+  fn decodes_html_entities_in_attribute_values() {
+    assert_eq!(
+      find_attribute_values("<a href=\"search.html?q=a&amp;page=2\">", &LINK_ATTRIBUTES),
+      ["search.html?q=a&page=2"]
+    );
+    assert_eq!(
+      decode_html_entities("Tom&#39;s &#x26; Jerry&apos;s &unknown; &amp"),
+      "Tom's & Jerry's &unknown; &amp"
+    );
+  }
+
+  #[test]
+  fn accepts_existing_targets() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    write_page(
+      temp_dir.path(),
+      "index.html",
+      "<a href=\"./Silver Blaze.html\">Story</a>\n<a href=\"posts/\">Posts</a>\n<a href=\"#top\">Top</a>",
+    );
+    write_page(
+      temp_dir.path(),
+      "Silver Blaze.html",
+      "<h2 id=\"chapter-1\">I</h2>\n<a href=\"#chapter-1\">",
+    );
+    write_page(
+      temp_dir.path(),
+      "posts/index.html",
+      "<a href=\"../Silver%20Blaze.html#chapter-1\">Back</a>\n<a href=\"Q&amp;A.html\">Q&amp;A</a>",
+    );
+    write_page(temp_dir.path(), "posts/Q&A.html", "<h1>Q&amp;A</h1>");
+
+    assert!(check_links(temp_dir.path()).is_empty());
+
+    temp_dir.close().expect("Delete test directory");
+  }
+
+  #[test]
+  fn reports_missing_files_and_fragments_with_line() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    write_page(
+      temp_dir.path(),
+      "index.html",
+      "<p>\n<a href=\"missing.html\">Missing</a>\n<a href=\"other.html#nowhere\">Other</a>\n<img \
+       src=\"../outside.png\">",
+    );
+    write_page(temp_dir.path(), "other.html", "<h1 id=\"somewhere\">Other</h1>");
+
+    let broken_links = check_links(temp_dir.path());
+    let targets: Vec<(usize, &str)> = broken_links
+      .iter()
+      .map(|link| (link.line, link.target.as_str()))
+      .collect();
+
+    assert_eq!(
+      targets,
+      [(2, "missing.html"), (3, "other.html#nowhere"), (4, "../outside.png")]
+    );
+    assert_eq!(broken_links[0].file, temp_dir.path().join("index.html"));
+
+    temp_dir.close().expect("Delete test directory");
+  }
+}
@@ -1,573 +1,831 @@
-use clap::Parser;
-use serde::{Deserialize, Serialize};
-use std::fs::File;
-use std::io::{self, BufRead, BufReader, Seek, Write};
-use std::{fs, path};
-#[derive(Debug, Deserialize, Serialize)]
-#[serde_with::skip_serializing_none]
-struct Config {
-  input: Option<String>,
-  output: Option<String>,
-  lang: Option<String>,
-}
-
-const HTML_TEMPLATE: &str = "<!DOCTYPE html>\n<html lang=\"{{lang}}\">\n<head>\n\t<meta charset=\"UTF-8\">\n\t<meta \
-                             http-equiv=\"X-UA-Compatible\" content=\"IE=edge\">\n\t<meta name=\"viewport\" \
-                             content=\"width=device-width, \
-                             initial-scale=1.0\">\n\t<title>\n\t\t{{title}}\n\t</title>\n</head>\n<body>\n";
-const DEFAULT_OUTPUT_DIR: &str = "./dist";
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand};
+use serde_json::{Map, Value};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use rost_gen::{
+  config, link_checker, BuildReport, BuildSettings, Error, OutputFormat, DEFAULT_LANG, DEFAULT_OUTPUT_DIR,
+  DEFAULT_SHORTCODES_DIR,
+};
+use std::{env, fs, path, process};
+
+mod scaffold;
+mod serve;
+mod watch;
+
+const DEFAULT_PORT: u16 = 3000;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-  /// Take config written in JSON file as argument to parse into system
-  #[arg(short, long, value_name = "CONFIG_PATH")]
+  #[command(subcommand)]
+  command: Option<Command>,
+
+  /// Take config written in a JSON, TOML or YAML file as argument to parse
+  /// into system. Looks for rost_gen.toml, rost_gen.yaml, rost_gen.yml or
+  /// rost_gen.json in the current directory and its parents by default
+  #[arg(short, long, value_name = "CONFIG_PATH", global = true)]
   config: Option<String>,
 
+  /// Read the config file in this format instead of guessing from its
+  /// extension
+  #[arg(long, value_enum, global = true)]
+  config_format: Option<config::ConfigFormat>,
+
+  // Whether the config file was found by looking through directories rather
+  // than given with -c
+  #[arg(skip)]
+  config_discovered: bool,
+
   /// Convert file/files in directory at INPUT_PATH into html files, outputting
   /// into ./dist directory by default (deleting existing contents)
-  #[arg(short, long, value_name = "INPUT_PATH")]
+  #[arg(short, long, value_name = "INPUT_PATH", global = true)]
   input: Option<String>,
 
   /// Optional: Output generated files to directory at OUTPUT_PATH
-  #[arg(short, long, value_name = "OUTPUT_PATH", default_value = DEFAULT_OUTPUT_DIR)]
+  #[arg(short, long, value_name = "OUTPUT_PATH", default_value = DEFAULT_OUTPUT_DIR, global = true)]
   output: String,
 
+  /// Optional: Write pages as html, plain text or a JSON tree of each page
+  #[arg(long, value_enum, default_value_t = OutputFormat::Html, global = true)]
+  output_format: OutputFormat,
+
   /// Optional: Specify lang attribute of html tag
-  #[arg(short, long, value_name = "LANG", default_value = "en-CA")]
+  #[arg(short, long, value_name = "LANG", default_value = DEFAULT_LANG, global = true)]
   lang: String,
+
+  /// Optional: Add a permalink to each heading, shown when hovering over it
+  #[arg(long, global = true)]
+  permalinks: bool,
+
+  /// Optional: Detect chapter headings in .txt files (e.g. "CHAPTER IV", "I.",
+  /// short all caps lines), adding a table of contents of the chapters
+  #[arg(long, global = true)]
+  chapters: bool,
+
+  /// Optional: Join hard-wrapped lines in .txt files into flowing paragraphs,
+  /// keeping line breaks after short or indented lines
+  #[arg(long, global = true)]
+  reflow: bool,
+
+  /// Optional: Remove Project Gutenberg headers and licence text from .txt
+  /// files, using the title and author from the header
+  #[arg(long, global = true)]
+  strip_gutenberg: bool,
+
+  /// Optional: Convert straight quotes, dashes (--, ---) and ellipses (...)
+  /// into typographic characters, using quotes for the lang of the html
+  #[arg(long, global = true)]
+  smart_typography: bool,
+
+  /// Optional: Replace emoji shortcodes like :rocket: in .md files with emoji,
+  /// leaving code and unknown shortcodes as they are
+  #[arg(long, global = true)]
+  emoji: bool,
+
+  /// Optional: Replace emoji shortcodes in .txt files too, with --emoji
+  #[arg(long, global = true)]
+  emoji_in_txt: bool,
+
+  /// Optional: Convert "Term" lines followed by ": definition" lines in .md
+  /// files into definition lists
+  #[arg(long, global = true)]
+  definition_lists: bool,
+
+  /// Optional: Define abbreviations in .md files with "*[HTML]: HyperText
+  /// Markup Language" lines, marking each use of them with <abbr>
+  #[arg(long, global = true)]
+  abbreviations: bool,
+
+  /// Optional: Convert ^text^ in .md files into superscript
+  #[arg(long, global = true)]
+  superscript: bool,
+
+  /// Optional: Convert ~text~ in .md files into subscript
+  #[arg(long, global = true)]
+  subscript: bool,
+
+  /// Optional: Use the html file at TEMPLATE_PATH as the page template, with
+  /// {{lang}}, {{title}} and {{content}} replaced for each page
+  #[arg(short, long, value_name = "TEMPLATE_PATH", global = true)]
+  template: Option<String>,
+
+  /// Optional: Read shortcode templates from the .html files in
+  /// SHORTCODES_PATH [default: shortcodes]
+  #[arg(long, value_name = "SHORTCODES_PATH", global = true)]
+  shortcodes: Option<String>,
+
+  /// Optional: After converting, check generated files for broken relative
+  /// links and #fragments, exiting with an error if any are found
+  #[arg(long, global = true)]
+  check_links: bool,
+
+  /// Print each config value after merging defaults, the config file,
+  /// ROST_GEN_* environment variables and flags, and where it came from
+  #[arg(long, global = true)]
+  print_config: bool,
+
+  /// Optional: Stop at the first file that can't be converted, instead of
+  /// converting the rest and reporting the failures at the end
+  #[arg(long, global = true)]
+  fail_fast: bool,
+
+  /// Optional: Keep running after converting, rebuilding whenever input files,
+  /// the config file or the template change
+  #[arg(short, long, global = true)]
+  watch: bool,
 }
 
-fn main() {
-  // let args: Vec<String> = env::args().collect();
-  let args = Args::parse();
+#[derive(Subcommand)]
+enum Command {
+  /// Convert the input into html files (the default when -i or -c is given)
+  Build,
+  /// Create a config, template, sample page and stylesheet for a new site
+  Init {
+    /// Directory to create the site in
+    #[arg(default_value = ".")]
+    dir: String,
+  },
+  /// Create a markdown post named after today's date and the title, in the
+  /// input directory
+  New {
+    /// Title of the post
+    title: String,
+  },
+  /// Delete the output directory
+  Clean,
+  /// Convert the input, then serve the output directory on localhost,
+  /// rebuilding and reloading open pages whenever files change
+  Serve {
+    /// Port to listen on
+    #[arg(short, long, default_value_t = DEFAULT_PORT)]
+    port: u16,
+  },
+  /// Work with the config file
+  Config {
+    #[command(subcommand)]
+    action: ConfigCommand,
+  },
+}
 
-  if let Some(input) = args.input.as_deref() {
-    handle_conversion(input, &args.output, &args.lang);
-  } else if let Some(config) = args.config.as_deref() {
-    handle_config(config);
-  }
+#[derive(Subcommand)]
+enum ConfigCommand {
+  /// Check the config file, environment variables and flags for errors
+  /// without building
+  Check,
 }
 
-fn handle_config(config: &str) {
-  let config_path = config.to_string();
-  let path = path::Path::new(&config_path);
+fn main() {
+  // let args: Vec<String> = env::args().collect();
+  let matches = Args::command().get_matches();
+  let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
+  let command_line_values = command_line_values(&args, &matches);
 
-  if !path.exists() {
-    println!("Invalid path: No file or directory found at '{config_path}'");
-    return;
+  if args.config.is_none() && !matches!(args.command, Some(Command::Init { .. })) {
+    args.config = env::current_dir()
+      .ok()
+      .and_then(|current_dir| config::discover_config_file(&current_dir))
+      .map(|config_path| relative_to_current_dir(&config_path).to_string_lossy().to_string());
+    args.config_discovered = args.config.is_some();
   }
 
-  if path.is_file() && path.extension().unwrap().to_str().unwrap() == "json" {
-    println!("reading json file at  '{config_path}'");
-    let file = File::open(config_path).unwrap();
-    let reader = BufReader::new(file);
-
-    let construct: Config = serde_json::from_reader(reader).unwrap();
-    let dept_input = construct.input.unwrap_or_else(|| " ".to_string());
-    let dept_output = construct.output.unwrap_or_else(|| DEFAULT_OUTPUT_DIR.to_string());
-    let dept_lang = construct.lang.unwrap_or_else(|| "en-CA".to_string());
-
-    handle_conversion(&dept_input, &dept_output, &dept_lang)
+  let result = if args.print_config {
+    layered_config(&args, &command_line_values).map(|layered_config| print!("{}", layered_config.describe()))
   } else {
-    println!("Only .json files are accepted");
+    match &args.command {
+      Some(Command::Init { dir }) => handle_init(dir),
+      Some(Command::New { title }) => handle_new(&args, &command_line_values, title),
+      Some(Command::Clean) => handle_clean(&args, &command_line_values),
+      Some(Command::Config {
+        action: ConfigCommand::Check,
+      }) => handle_config_check(&args, &command_line_values),
+      Some(Command::Build) | Some(Command::Serve { .. }) | None => handle_build(args, &command_line_values),
+    }
+  };
+
+  if let Err(error) = result {
+    eprintln!("Error: {error}");
+    process::exit(error.exit_code());
   }
 }
 
-fn handle_conversion(input: &str, output_dir_path: &String, html_lang: &str) {
-  let input_path = input.to_string();
-  let path = path::Path::new(&input_path);
+// Config values for the flags that were given on the command line, leaving out
+// defaults so they don't override the config file
+fn command_line_values(args: &Args, matches: &ArgMatches) -> Map<String, Value> {
+  // Global flags can be given after any subcommand e.g. `config check -i docs`
+  let all_matches: Vec<&ArgMatches> = std::iter::successors(Some(matches), |matches| {
+    matches.subcommand().map(|(_, subcommand_matches)| subcommand_matches)
+  })
+  .collect();
+  let given = |key: &str| {
+    all_matches
+      .iter()
+      .any(|matches| matches.value_source(key) == Some(ValueSource::CommandLine))
+  };
+
+  let mut values = config::to_values(&config::Config {
+    input: args.input.clone(),
+    output: Some(args.output.clone()),
+    output_format: Some(args.output_format),
+    lang: Some(args.lang.clone()),
+    permalinks: Some(args.permalinks),
+    chapters: Some(args.chapters),
+    reflow: Some(args.reflow),
+    strip_gutenberg: Some(args.strip_gutenberg),
+    smart_typography: Some(args.smart_typography),
+    emoji: Some(args.emoji),
+    emoji_in_txt: Some(args.emoji_in_txt),
+    definition_lists: Some(args.definition_lists),
+    abbreviations: Some(args.abbreviations),
+    superscript: Some(args.superscript),
+    subscript: Some(args.subscript),
+    template: args.template.clone(),
+    shortcodes: args.shortcodes.clone(),
+    fail_fast: Some(args.fail_fast),
+    ..Default::default()
+  });
+  values.retain(|key, _| given(key));
+  values
+}
 
-  if !path.exists() {
-    println!("Invalid path: No file or directory found at '{input_path}'");
-    return;
+// Merge the defaults, config file, environment variables and command line
+fn layered_config(args: &Args, command_line_values: &Map<String, Value>) -> Result<config::LayeredConfig, Error> {
+  let mut layered_config = config::LayeredConfig::with_defaults();
+
+  if let Some(config_path) = args.config.as_deref() {
+    // Written to stderr, so printed config can be piped to other tools
+    eprintln!("Reading config file at '{config_path}'");
+    let mut config_values = config::read_config_file(config_path, args.config_format)?;
+    // Paths in a config file found in a parent directory are relative to that
+    // directory
+    if args.config_discovered {
+      let config_dir = path::Path::new(config_path).parent().unwrap_or(path::Path::new(""));
+      config::resolve_paths(&mut config_values, config_dir);
+    }
+    layered_config.merge(config_values, config::Source::ConfigFile(config_path.to_string()));
   }
 
-  create_output_directory(output_dir_path);
-
-  if path.is_dir() {
-    let dir = fs::read_dir(&input_path).expect("Read input directory");
-    println!("Converting files in directory at {input_path}");
-    convert_files_in_directory(dir, output_dir_path, html_lang);
-  }
+  layered_config.merge_env_vars(env::vars());
+  layered_config.merge(command_line_values.clone(), config::Source::CommandLine);
+  Ok(layered_config)
+}
 
-  if path.is_file() && conversion_file_path_valid(path) {
-    convert_file(&input_path, path, output_dir_path, html_lang);
-  } else {
-    println!("Only .txt or .md files are accepted");
-    return;
+// Shorten a path in the current directory or one of its parents e.g.
+// /home/site/rost_gen.toml into ../rost_gen.toml
+fn relative_to_current_dir(path: &path::Path) -> path::PathBuf {
+  let current_dir = match env::current_dir() {
+    Ok(current_dir) => current_dir,
+    Err(_) => return path.to_path_buf(),
+  };
+
+  for (depth, ancestor) in current_dir.ancestors().enumerate() {
+    if let Ok(relative_path) = path.strip_prefix(ancestor) {
+      let mut shortened_path: path::PathBuf = std::iter::repeat_n("..", depth).collect();
+      shortened_path.push(relative_path);
+      return shortened_path;
+    }
   }
 
-  println!("Conversion successful. Output file(s) placed in directory at {output_dir_path}");
+  path.to_path_buf()
 }
 
-fn create_output_directory(output_dir_path: &String) {
-  // Delete output dir and its contents if it is the default output dir
-  if output_dir_path == DEFAULT_OUTPUT_DIR && path::Path::new(DEFAULT_OUTPUT_DIR).exists() {
-    fs::remove_dir_all(DEFAULT_OUTPUT_DIR).expect("Delete existing output directory")
-  }
-  fs::create_dir_all(output_dir_path).expect("Create output directory");
+fn read_config(args: &Args, command_line_values: &Map<String, Value>) -> Result<config::Config, Error> {
+  layered_config(args, command_line_values)?
+    .config()
+    .map_err(|message| Error::Config { path: None, message })
 }
 
-fn convert_files_in_directory(dir: fs::ReadDir, output_dir_path: &String, html_lang: &str) {
-  // Iterate over each file in directory, calling the convert file function
-  for entry in dir {
-    let path_string = &entry
-      .expect("Read directory files")
-      .path()
-      .to_str()
-      .unwrap()
-      .to_string();
-    let path = path::Path::new(path_string);
-    convert_file(path_string, path, output_dir_path, html_lang);
+// Build the site, printing what was converted and any problems
+fn build(settings: &BuildSettings) -> Result<BuildReport, Error> {
+  println!("Converting files at {}", settings.input);
+  let report = rost_gen::build(settings)?;
+  if report.failures.is_empty() {
+    println!(
+      "Conversion successful. Output file(s) placed in directory at {}",
+      settings.output_dir_path
+    );
   }
-}
-
-fn convert_file(path_string: &String, path: &path::Path, output_dir_path: &String, html_lang: &str) {
-  // We only want to convert .txt files
-  if !conversion_file_path_valid(path) {
-    return;
-  }
-
-  println!("Converting file at {path_string}");
-
-  // Variables to read input file
-  let in_file = fs::File::open(path_string).unwrap_or_else(|_| panic!("Open file at {path_string}"));
-  let mut buf_reader = io::BufReader::new(in_file);
-  let mut read_buffer = String::new();
-
-  // Try to find title
-  // Title is first line followed by two blank lines
-  let mut title = String::new();
-  let mut read_bytes_count: u64 = parse_title_from_file(path_string, &mut title);
-
-  // Variables to create and write to output html file
-  let html_file_name = path.file_stem().unwrap().to_str().unwrap();
-  let out_file_path = path::PathBuf::from(output_dir_path).join(format!("{html_file_name}.html"));
-  let mut out_file = fs::OpenOptions::new()
-    .append(true)
-    .create(true)
-    .open(out_file_path)
-    .expect("Generate html file");
-
-  // Replace lang and title in the template with appropriate values
-  // If title was not found, file name will be used instead
-  let html_template = HTML_TEMPLATE.replace("{{lang}}", html_lang).replace(
-    "{{title}}",
-    if title.is_empty() {
-      html_file_name
-    } else {
-      title.as_str()
-    },
-  );
-
-  // Write the html template
-  write!(out_file, "{}", html_template).expect("Generate html file");
-
-  // Write the title if found
-  if title.is_empty() {
-    writeln!(out_file, "\t<p>").expect("Generate html file");
-  } else {
-    write!(out_file, "\t<h1>\n\t\t{title}\t</h1>\n\t<p>\n").expect("Generate html file");
-    // Skip the title bytes (first three lines) to prevent printing title twice
-    buf_reader
-      .seek(io::SeekFrom::Start(read_bytes_count))
-      .expect("Read input file");
-  }
-
-  // Write the rest of the contents
-  while read_bytes_count < fs::metadata(path_string).expect("Read input file").len() {
-    read_buffer.clear();
-    read_bytes_count += buf_reader.read_line(&mut read_buffer).expect("Read input file") as u64;
-    // Add paragraph tags if line is an empty line
-    // Empty line indicate end of current paragraph and start of next paragraph
-    if path.extension().unwrap().to_str().unwrap() == "txt" {
-      if read_buffer == "\n" || read_buffer == "\r\n" {
-        write!(out_file, "\t</p>\n\t<p>\n").expect("Generate html file");
-      } else {
-        write!(out_file, "\t\t{}", read_buffer.clone()).expect("Generate html file");
-      }
-    }
-    if path.extension().unwrap().to_str().unwrap() == "md" {
-      if read_buffer == "\n" || read_buffer == "\r\n" {
-        write!(out_file, "\t</p>\n\t<p>").expect("Generate html file");
-      }
-
-      if let Some(content) = read_buffer.strip_prefix("# ") {
-        write!(out_file, "<h1>\n{}</h1>\n", content).expect("Generate html file");
-      } else if read_buffer == "---\n" || read_buffer == "---\r\n" {
-        writeln!(out_file, "<hr />\n").expect("Generate html file");
-      } else {
-        let processed_line = process_link_markdown(&read_buffer);
-        write!(out_file, "\t\t{}", processed_line.clone()).expect("Generate html file");
-      }
-    }
+  if let Some(page_count) = report.sitemap_pages {
+    println!("Wrote sitemap.xml listing {page_count} page(s)");
   }
-
-  // Write closing tags for html file
-  writeln!(out_file, "\n\t</p>\n</body>\n</html>").expect("Generate html file");
+  print_summary(&report);
+  Ok(report)
 }
 
-// returns no of bytes to skip if title is found, otherwise 0
-fn parse_title_from_file(path_string: &String, title: &mut String) -> u64 {
-  let mut buf_reader =
-    io::BufReader::new(fs::File::open(path_string).unwrap_or_else(|_| panic!("Open file at {path_string}")));
-  let mut line1 = String::new();
-  let mut line2 = String::new();
-  let mut line3 = String::new();
-  let lines = [&mut line1, &mut line2, &mut line3];
-  let mut read_bytes: u64 = 0;
-
-  for line in lines {
-    match buf_reader.read_line(line) {
-      Ok(n) => read_bytes += n as u64,
-      Err(_) => return 0,
-    }
+// Print warnings and failures to stderr, followed by the totals
+fn print_summary(report: &BuildReport) {
+  for warning in &report.warnings {
+    eprintln!("Warning: {warning}");
   }
-
-  // Check if line 1 is not empty and not a blank line, and is followed by two
-  // empty lines
-  if (!line1.is_empty() && line1 != "\n" && line1 != "\r\n")
-    && (line2 == "\n" || line2 == "\r\n")
-    && (line3 == "\n" || line3 == "\r\n")
-  {
-    *title = line1;
-    read_bytes
-  } else {
-    0
+  for failure in &report.failures {
+    eprintln!("Error: {failure}");
   }
+  println!(
+    "{} converted, {} copied, {} skipped, {} failed",
+    report.converted,
+    report.copied,
+    report.skipped,
+    report.failures.len()
+  );
 }
 
-fn conversion_file_path_valid(path: &path::Path) -> bool {
-  let extension = path.extension().unwrap().to_str().unwrap();
+fn handle_build(args: Args, command_line_values: &Map<String, Value>) -> Result<(), Error> {
+  let config = read_config(&args, command_line_values)?;
 
-  if extension == "txt" || extension == "md" {
-    return true;
+  // Nothing to do, so show how to use the tool
+  if config.input.is_none() && args.command.is_none() && args.config.is_none() {
+    Args::command().print_help().expect("Print help");
+    return Ok(());
   }
 
-  false
-}
+  let settings = BuildSettings::from_config(config)?;
+  let report = build(&settings)?;
+  if !report.failures.is_empty() {
+    return Err(Error::Build(report.failures));
+  }
 
-fn process_link_markdown(line: &String) -> String {
-  const LINK_HTML_TEMPLATE: &str = "<a href=\"URL\">TEXT</a>";
-  let line_bytes = line.as_bytes();
-  let mut link_start_found = false;
-  let mut link_end_found = false;
-  let mut link_url_start_found = false;
-  let mut link_url_end_found = false;
-  let mut link_start = 0;
-  let mut link_end = 0;
-  let mut link_url_start = 0;
-  let mut link_url_end = 0;
-
-  for (i, &char) in line_bytes.iter().enumerate() {
-    if !link_start_found && char == b'[' {
-      link_start_found = true;
-      link_start = i + 1;
+  if args.check_links {
+    println!("Checking links in directory at {}", settings.output_dir_path);
+    let broken_links = link_checker::check_links(path::Path::new(&settings.output_dir_path));
+    for broken_link in &broken_links {
+      eprintln!("{broken_link}");
     }
-
-    if link_start_found && !link_end_found && char == b']' {
-      link_end_found = true;
-      link_end = i;
+    if !broken_links.is_empty() {
+      return Err(Error::Check(format!(
+        "Found {} broken link(s) in '{}'",
+        broken_links.len(),
+        settings.output_dir_path
+      )));
     }
+    println!("No broken links found");
+  }
+
+  if let Some(Command::Serve { port }) = args.command {
+    // Bumped after each rebuild, telling open pages to reload
+    let reload_version = Arc::new(AtomicU64::new(0));
+    let root = path::PathBuf::from(&settings.output_dir_path);
+    let address = serve::start_server(root, port, Arc::clone(&reload_version)).map_err(|cause| Error::Io {
+      path: None,
+      action: format!("start server on port {port}"),
+      cause,
+    })?;
+    println!("Serving {} at http://{address}", settings.output_dir_path);
+    watch_for_changes(settings, &args, command_line_values, || {
+      reload_version.fetch_add(1, Ordering::SeqCst);
+    });
+  } else if args.watch {
+    watch_for_changes(settings, &args, command_line_values, || {});
+  }
+
+  Ok(())
+}
 
-    if link_end_found {
-      if !link_url_start_found && char == b'(' {
-        link_url_start_found = true;
-        link_url_start = i + 1;
-      }
-
-      if link_url_start_found && !link_url_end_found && char == b')' {
-        link_url_end_found = true;
-        link_url_end = i;
-      }
-    }
+fn handle_init(dir: &str) -> Result<(), Error> {
+  let created_files =
+    scaffold::init_site(path::Path::new(dir)).map_err(|cause| Error::io("create site in", dir, cause))?;
+  for file_path in created_files {
+    println!("Created {}", file_path.display());
   }
+  println!("Build the site by running 'rost_gen build' in {dir}");
+  Ok(())
+}
 
-  if link_start_found && link_end_found {
-    let link_text = &line[link_start..link_end];
-    let mut link_url = "";
-    let mut link_html = LINK_HTML_TEMPLATE.replace("TEXT", link_text);
-
-    if link_url_start_found && link_url_end_found {
-      link_url = &line[link_url_start..link_url_end];
-    }
-
-    link_html = link_html.replace("URL", link_url);
-
-    if link_start > 1 {
-      link_html = format!("{}{link_html}", &line[0..(link_start - 1)]);
-    }
-
-    if (link_url_end + 1) < line_bytes.len() {
-      link_html = format!("{link_html}{}", &line[(link_url_end + 1)..]);
-    }
+fn handle_config_check(args: &Args, command_line_values: &Map<String, Value>) -> Result<(), Error> {
+  let config = read_config(args, command_line_values)?;
 
-    return link_html;
+  let problems = config_problems(&config);
+  if !problems.is_empty() {
+    return Err(Error::Config {
+      path: None,
+      message: problems.join("\n  "),
+    });
   }
 
-  line.clone()
+  match &args.config {
+    Some(config_path) => println!("Config in '{config_path}' is valid"),
+    None => println!("Config is valid"),
+  }
+  Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-  use std::io::Read;
-
-  use crate::*;
+// Problems with a config that parsed, but can't be built
+fn config_problems(config: &config::Config) -> Vec<String> {
+  let mut problems = Vec::new();
 
-  #[test]
-  fn converts_txt_files() {
-    let input_file_path = path::Path::new("sample.txt");
-    assert!(conversion_file_path_valid(input_file_path));
+  match &config.input {
+    None => problems.push(Error::missing_input().to_string()),
+    Some(input) if !path::Path::new(input).exists() => {
+      problems.push(format!("Invalid input: No file or directory found at '{input}'"))
+    }
+    _ => {}
   }
 
-  #[test]
-  fn converts_md_files() {
-    let input_file_path = path::Path::new("sample.md");
-    assert!(conversion_file_path_valid(input_file_path));
+  if let Some(template) = config
+    .template
+    .as_ref()
+    .filter(|template| !path::Path::new(template).is_file())
+  {
+    problems.push(format!("Invalid template: No file found at '{template}'"));
   }
 
-  #[test]
-  fn does_not_convert_unsupported_file_types() {
-    let input_file_path = path::Path::new("sample.exe");
-    assert!(!conversion_file_path_valid(input_file_path));
+  // The default shortcodes directory is optional
+  if let Some(shortcodes) = config
+    .shortcodes
+    .as_ref()
+    .filter(|shortcodes| *shortcodes != DEFAULT_SHORTCODES_DIR && !path::Path::new(shortcodes).is_dir())
+  {
+    problems.push(format!("Invalid shortcodes: No directory found at '{shortcodes}'"));
   }
 
-  #[test]
-  fn parses_title_when_provided() {
-    let temp_dir = tempfile::tempdir().unwrap();
-    let test_input_path = temp_dir.path().join("parse_title_test.txt");
-    let test_input_path_string = test_input_path.as_os_str().to_str().unwrap().to_string();
-    let mut test_input_file = File::create(&test_input_path).unwrap();
-    writeln!(test_input_file, "test\n\n").expect("Create test input file");
-    let mut output_title = String::new();
+  problems
+}
 
-    parse_title_from_file(&test_input_path_string, &mut output_title);
+fn handle_new(args: &Args, command_line_values: &Map<String, Value>, title: &str) -> Result<(), Error> {
+  // Posts go in the input directory, or the current directory if the input is a
+  // single file or wasn't given
+  let posts_dir = read_config(args, command_line_values)?
+    .input
+    .map(path::PathBuf::from)
+    .filter(|input_path| input_path.is_dir())
+    .unwrap_or_else(|| path::PathBuf::from("."));
+
+  let post_path =
+    scaffold::new_post(&posts_dir, title).map_err(|cause| Error::io("create post in", &posts_dir, cause))?;
+  println!("Created {}", post_path.display());
+  Ok(())
+}
 
-    assert_eq!(output_title, "test\n");
+fn handle_clean(args: &Args, command_line_values: &Map<String, Value>) -> Result<(), Error> {
+  let config = read_config(args, command_line_values)?;
+  let output_dir_path = config.output.as_deref().unwrap_or(DEFAULT_OUTPUT_DIR);
 
-    drop(test_input_file);
-    temp_dir.close().expect("Delete test directory");
+  match clean_output_directory(output_dir_path, config.input.as_deref()) {
+    Ok(true) => println!("Deleted output directory at {output_dir_path}"),
+    Ok(false) => println!("Nothing to clean: no directory found at '{output_dir_path}'"),
+    Err(message) => return Err(Error::Check(format!("Did not delete '{output_dir_path}': {message}"))),
   }
+  Ok(())
+}
 
-  #[test]
-  fn returns_title_size_when_found() {
-    let temp_dir = tempfile::tempdir().unwrap();
-    let test_input_path = temp_dir.path().join("parse_title_test.txt");
-    let test_input_path_string = test_input_path.as_os_str().to_str().unwrap().to_string();
-    let mut test_input_file = File::create(&test_input_path).unwrap();
-    writeln!(test_input_file, "test\n\n").expect("Create test input file");
-    let mut output_title = String::new();
-
-    let bytes_read = parse_title_from_file(&test_input_path_string, &mut output_title);
-
-    assert_eq!(bytes_read, 7);
+// Rebuild the files affected by each change until the process is stopped
+// Errors are reported, but don't stop watching
+fn watch_for_changes(
+  mut settings: BuildSettings,
+  args: &Args,
+  command_line_values: &Map<String, Value>,
+  mut on_rebuild: impl FnMut(),
+) {
+  let config = args.config.as_deref();
+  // Files included by pages are watched too, wherever they are
+  let watched_paths = |settings: &BuildSettings, includes: &PageIncludes| {
+    let mut paths = vec![path::PathBuf::from(&settings.input)];
+    paths.extend(config.map(path::PathBuf::from));
+    paths.extend(settings.template_path.as_ref().map(path::PathBuf::from));
+    paths.extend(settings.shortcodes_dir_path.as_ref().map(path::PathBuf::from));
+    paths.extend(includes.values().flatten().cloned().collect::<BTreeSet<_>>());
+    paths
+  };
+  let mut includes = page_includes(&settings.input);
+  let mut paths = watched_paths(&settings, &includes);
+  let mut snapshot = watch::take_snapshot(&paths);
+  println!("Watching for changes in {}. Press Ctrl+C to stop", settings.input);
+
+  loop {
+    let changes = watch::wait_for_changes(&paths, &mut snapshot);
+    let rebuild_start = Instant::now();
+
+    // A changed config, template or shortcode can affect every file, so rebuild
+    // everything
+    let changed = |file: Option<&str>| file.is_some_and(|file| changes.modified.contains(&path::PathBuf::from(file)));
+    let shortcode_changed = settings.shortcodes_dir_path.as_ref().is_some_and(|dir_path| {
+      changes
+        .modified
+        .iter()
+        .chain(&changes.removed)
+        .any(|changed_path| changed_path.starts_with(dir_path))
+    });
+    if changed(config) || changed(settings.template_path.as_deref()) || shortcode_changed {
+      let rebuild = read_config(args, command_line_values)
+        .and_then(BuildSettings::from_config)
+        .and_then(|new_settings| build(&new_settings).map(|_| new_settings));
+      match rebuild {
+        Ok(new_settings) => {
+          settings = new_settings;
+          includes = page_includes(&settings.input);
+          paths = watched_paths(&settings, &includes);
+          snapshot = watch::take_snapshot(&paths);
+          println!(
+            "Config, template or shortcodes changed, rebuilt all files in {}ms",
+            rebuild_start.elapsed().as_millis()
+          );
+          on_rebuild();
+        }
+        Err(error) => eprintln!("Error: {error}"),
+      }
+      continue;
+    }
 
-    drop(test_input_file);
-    temp_dir.close().expect("Delete test directory");
+    let rebuilt = rebuild_changed_files(&settings, &changes, &mut includes);
+    // Pages may now include different files
+    let new_paths = watched_paths(&settings, &includes);
+    if new_paths != paths {
+      paths = new_paths;
+      snapshot = watch::take_snapshot(&paths);
+    }
+    if rebuilt.converted > 0 || rebuilt.copied > 0 || rebuilt.removed > 0 {
+      let finalised = rost_gen::write_sitemap(&settings.output_dir_path, &settings.options).and_then(|_| {
+        settings
+          .options
+          .hooks
+          .finalise(path::Path::new(&settings.output_dir_path))
+      });
+      if let Err(error) = finalised {
+        eprintln!("Error: {error}");
+      }
+      println!(
+        "Rebuilt {} file(s), copied {} file(s), removed {} file(s) in {}ms",
+        rebuilt.converted,
+        rebuilt.copied,
+        rebuilt.removed,
+        rebuild_start.elapsed().as_millis()
+      );
+      on_rebuild();
+    }
   }
+}
 
-  #[test]
-  fn does_not_change_title_arg_when_no_title() {
-    let temp_dir = tempfile::tempdir().unwrap();
-    let test_input_path = temp_dir.path().join("parse_title_test.txt");
-    let test_input_path_string = test_input_path.as_os_str().to_str().unwrap().to_string();
-    let mut test_input_file = File::create(&test_input_path).unwrap();
-    writeln!(test_input_file, "test\n").expect("Create test input file");
-    let mut output_title = String::new();
+// How many files a rebuild after a change converted, copied and removed
+#[derive(Debug, Default, PartialEq, Eq)]
+struct RebuiltFiles {
+  converted: usize,
+  copied: usize,
+  removed: usize,
+}
 
-    parse_title_from_file(&test_input_path_string, &mut output_title);
+// The files each page in the input includes, as canonical paths
+type PageIncludes = HashMap<path::PathBuf, Vec<path::PathBuf>>;
 
-    assert_eq!(output_title, "");
+// Pages that can't be read are left out, as converting them reports the error
+fn page_includes(input: &str) -> PageIncludes {
+  let input_path = path::Path::new(input);
+  let page_paths: Vec<path::PathBuf> = if input_path.is_dir() {
+    let entries = fs::read_dir(input_path).into_iter().flatten().flatten();
+    entries.map(|entry| entry.path()).collect()
+  } else {
+    vec![input_path.to_path_buf()]
+  };
+
+  page_paths
+    .into_iter()
+    .filter(|page_path| rost_gen::conversion_file_path_valid(page_path))
+    .filter_map(|page_path| {
+      let included_paths = rost_gen::included_files(&page_path).ok()?;
+      Some((page_path, included_paths))
+    })
+    .collect()
+}
 
-    drop(test_input_file);
-    temp_dir.close().expect("Delete test directory");
+// Convert changed pages, and pages including a changed file, and copy changed
+// assets again, and delete the output of input files that no longer exist
+fn rebuild_changed_files(
+  settings: &BuildSettings,
+  changes: &watch::Changes,
+  includes: &mut PageIncludes,
+) -> RebuiltFiles {
+  let mut rebuilt = RebuiltFiles::default();
+  let mut page_paths = Vec::new();
+  // Included files outside the input aren't pages or assets of their own
+  let input_path = path::Path::new(&settings.input);
+  let in_input = |changed_path: &path::Path| changed_path == input_path || changed_path.parent() == Some(input_path);
+
+  for changed_path in changes.modified.iter().filter(|changed_path| in_input(changed_path)) {
+    if rost_gen::asset_file_path_valid(changed_path) {
+      println!("Copying file at {}", changed_path.display());
+      match rost_gen::copy_asset(changed_path, &settings.output_dir_path) {
+        Ok(()) => rebuilt.copied += 1,
+        Err(error) => eprintln!("Error: {error}"),
+      }
+    } else if rost_gen::conversion_file_path_valid(changed_path) {
+      page_paths.push(changed_path.clone());
+    }
   }
 
-  #[test]
-  fn returns_zero_when_no_title() {
-    let temp_dir = tempfile::tempdir().unwrap();
-    let test_input_path = temp_dir.path().join("parse_title_test.txt");
-    let test_input_path_string = test_input_path.as_os_str().to_str().unwrap().to_string();
-    let mut test_input_file = File::create(&test_input_path).unwrap();
-    writeln!(test_input_file, "test\n").expect("Create test input file");
-    let mut output_title = String::new();
+  let changed_paths: Vec<path::PathBuf> = changes
+    .modified
+    .iter()
+    .chain(&changes.removed)
+    .map(|changed_path| changed_path.canonicalize().unwrap_or_else(|_| changed_path.clone()))
+    .collect();
+  let mut including_pages: Vec<path::PathBuf> = includes
+    .iter()
+    .filter(|(page_path, included_paths)| {
+      !page_paths.contains(page_path) && included_paths.iter().any(|included| changed_paths.contains(included))
+    })
+    .map(|(page_path, _)| page_path.clone())
+    .collect();
+  including_pages.sort();
+  page_paths.append(&mut including_pages);
+
+  for page_path in &page_paths {
+    println!("Converting file at {}", page_path.display());
+    match rost_gen::convert_file(page_path, &settings.output_dir_path, &settings.options) {
+      Ok(warnings) => {
+        rebuilt.converted += 1;
+        for warning in rost_gen::file_warnings(page_path, warnings) {
+          eprintln!("Warning: {warning}");
+        }
+      }
+      Err(error) => eprintln!("Error: {error}"),
+    }
+    if let Ok(included_paths) = rost_gen::included_files(page_path) {
+      includes.insert(page_path.clone(), included_paths);
+    }
+  }
 
-    let bytes_read = parse_title_from_file(&test_input_path_string, &mut output_title);
+  for removed_path in changes.removed.iter().filter(|removed_path| in_input(removed_path)) {
+    includes.remove(removed_path);
+    let output_path = if rost_gen::conversion_file_path_valid(removed_path) {
+      rost_gen::output_file_path(removed_path, &settings.output_dir_path, settings.options.output_format)
+    } else if rost_gen::asset_file_path_valid(removed_path) {
+      path::Path::new(&settings.output_dir_path).join(removed_path.file_name().unwrap_or_default())
+    } else {
+      continue;
+    };
+    if fs::remove_file(output_path).is_ok() {
+      rebuilt.removed += 1;
+    }
+  }
 
-    assert_eq!(bytes_read, 0);
+  rebuilt
+}
 
-    drop(test_input_file);
-    temp_dir.close().expect("Delete test directory");
+fn clean_output_directory(output_dir_path: &str, input: Option<&str>) -> Result<bool, String> {
+  let output_path = path::Path::new(output_dir_path);
+  if !output_path.exists() {
+    return Ok(false);
   }
 
-  #[test]
-  fn processes_one_markdown_link() {
-    let input_line = String::from("[This is text for a link](www.example.com)");
-    let expected_output = "<a href=\"www.example.com\">This is text for a link</a>";
-    assert_eq!(process_link_markdown(&input_line), expected_output);
-  }
+  let output_path = output_path.canonicalize().map_err(|error| error.to_string())?;
+  let current_dir = env::current_dir()
+    .and_then(|current_dir| current_dir.canonicalize())
+    .map_err(|error| error.to_string())?;
+  let home_dir = env::var_os("HOME").and_then(|home_dir| path::Path::new(&home_dir).canonicalize().ok());
+  let input_path = input.and_then(|input| path::Path::new(input).canonicalize().ok());
 
-  #[test]
-  fn retains_text_before_link() {
-    let input_line = String::from("Lorem Ipsum[This is text for a link](www.example.com)");
-    let expected_output = "Lorem Ipsum<a href=\"www.example.com\">This is text for a link</a>";
-    assert_eq!(process_link_markdown(&input_line), expected_output);
+  if !output_path.is_dir() {
+    return Err("it is not a directory".to_string());
   }
-
-  #[test]
-  fn retains_text_after_link() {
-    let input_line = String::from("[This is text for a link](www.example.com)Lorem Ipsum");
-    let expected_output = "<a href=\"www.example.com\">This is text for a link</a>Lorem Ipsum";
-    assert_eq!(process_link_markdown(&input_line), expected_output);
+  if output_path.parent().is_none() || current_dir.starts_with(&output_path) {
+    return Err("it contains the current directory".to_string());
   }
-
-  #[test]
-  fn retains_text_around_link() {
-    let input_line = String::from("Lorem Ipsum[This is text for a link](www.example.com)Dolor Sit");
-    let expected_output = "Lorem Ipsum<a href=\"www.example.com\">This is text for a link</a>Dolor Sit";
-    assert_eq!(process_link_markdown(&input_line), expected_output);
+  if home_dir.is_some_and(|home_dir| home_dir.starts_with(&output_path)) {
+    return Err("it contains the home directory".to_string());
   }
-
-  #[test]
-  fn does_not_process_invalid_link_markdown() {
-    let input_line = String::from("[Invalid markdown[(www.example.com)");
-    let expected_output = "[Invalid markdown[(www.example.com)";
-    assert_eq!(process_link_markdown(&input_line), expected_output);
+  if input_path.is_some_and(|input_path| input_path.starts_with(&output_path)) {
+    return Err("it contains the input files".to_string());
   }
 
-  #[test]
-  fn image_link_test() {
-    let input_line = String::from("[First][Second](www.example.com)");
-    let expected_output = "<a href=\"www.example.com\">First</a>";
-    assert_eq!(process_link_markdown(&input_line), expected_output);
-  }
+  fs::remove_dir_all(&output_path).map_err(|error| error.to_string())?;
+  Ok(true)
+}
 
-  #[test]
-  fn process_link_markdown_returns_empty_string_arg() {
-    assert_eq!(process_link_markdown(&String::from("")), "");
-  }
+#[cfg(test)]
+mod tests {
+  use crate::*;
 
   #[test]
-  fn creates_output_directory() {
+  fn cleans_output_directory() {
     let temp_dir = tempfile::tempdir().unwrap();
-    let output_dir_path = temp_dir.path().join("test_dir");
-    let output_dir_path_string = output_dir_path.as_os_str().to_str().unwrap().to_string();
+    let output_dir_path = temp_dir.path().join("out");
+    fs::create_dir_all(&output_dir_path).expect("Create test output directory");
 
-    create_output_directory(&output_dir_path_string);
-    assert!(output_dir_path.is_dir());
+    assert_eq!(
+      clean_output_directory(output_dir_path.to_str().unwrap(), None),
+      Ok(true)
+    );
+    assert!(!output_dir_path.exists());
+    assert_eq!(
+      clean_output_directory(output_dir_path.to_str().unwrap(), None),
+      Ok(false)
+    );
 
     temp_dir.close().expect("Delete test directory");
   }
 
   #[test]
-  fn recreates_default_output_directory() {
-    let output_dir_path = path::Path::new(&DEFAULT_OUTPUT_DIR);
-    fs::create_dir_all(output_dir_path).expect("Create test default output dir");
-    File::create(output_dir_path.join("test_file.txt")).expect("Create test file");
-
-    create_output_directory(&DEFAULT_OUTPUT_DIR.to_string());
-
-    assert!(output_dir_path.is_dir());
-    assert!(output_dir_path.read_dir().unwrap().next().is_none());
-
-    fs::remove_dir_all(output_dir_path).expect("Delete test directory");
-  }
-
-  #[test]
-  fn retains_non_default_output_directory_contents() {
+  fn does_not_clean_directory_containing_input_or_current_directory() {
     let temp_dir = tempfile::tempdir().unwrap();
-    let output_dir_path = temp_dir.path().join("test_dir");
-    let output_dir_path_string = output_dir_path.as_os_str().to_str().unwrap().to_string();
-    let existing_file_path = output_dir_path.join("test_file.txt");
-    fs::create_dir(&output_dir_path).expect("Create test output dir");
-    File::create(&existing_file_path).expect("Create test file");
+    let input_path = temp_dir.path().join("content");
+    fs::create_dir_all(&input_path).expect("Create test input directory");
 
-    create_output_directory(&output_dir_path_string);
+    assert!(clean_output_directory(temp_dir.path().to_str().unwrap(), input_path.to_str()).is_err());
+    assert!(clean_output_directory(".", None).is_err());
+    assert!(input_path.exists());
 
-    assert!(output_dir_path.is_dir());
-    assert_eq!(
-      output_dir_path.read_dir().unwrap().next().unwrap().expect("").path(),
-      existing_file_path
-    )
+    temp_dir.close().expect("Delete test directory");
   }
 
   #[test]
-  fn creates_html_file() {
-    let temp_dir = tempfile::tempdir().unwrap();
-    let test_input_path = temp_dir.path().join("html_template_test.txt");
-    let test_input_path_string = test_input_path.as_os_str().to_str().unwrap().to_string();
-    let mut test_input_file = File::create(&test_input_path).unwrap();
-    writeln!(test_input_file, "test").expect("Create test input file");
-
-    convert_file(
-      &test_input_path_string,
-      test_input_path.as_path(),
-      &temp_dir.path().to_path_buf().into_os_string().into_string().unwrap(),
-      "en",
+  fn copies_changed_assets_and_converts_changed_pages() {
+    let temp_dir = tempfile::tempdir().expect("Create test directory");
+    let input_path = temp_dir.path().join("content");
+    let output_path = temp_dir.path().join("dist");
+    fs::create_dir_all(&input_path).expect("Create test directory");
+    fs::write(input_path.join("styles.css"), "p { color: red; }").expect("Create test file");
+    fs::write(input_path.join("page.md"), "First\n").expect("Create test file");
+    let settings = BuildSettings {
+      input: input_path.to_string_lossy().to_string(),
+      output_dir_path: output_path.to_string_lossy().to_string(),
+      template_path: None,
+      shortcodes_dir_path: None,
+      options: rost_gen::ConversionOptions::default(),
+    };
+    rost_gen::build(&settings).expect("Build test site");
+
+    fs::write(input_path.join("styles.css"), "p { color: blue; }").expect("Update test file");
+    fs::write(input_path.join("page.md"), "Second\n").expect("Update test file");
+    let changes = watch::Changes {
+      modified: vec![input_path.join("page.md"), input_path.join("styles.css")],
+      removed: Vec::new(),
+    };
+    assert_eq!(
+      rebuild_changed_files(&settings, &changes, &mut PageIncludes::new()),
+      RebuiltFiles {
+        converted: 1,
+        copied: 1,
+        removed: 0,
+      }
     );
+    assert_eq!(
+      fs::read_to_string(output_path.join("styles.css")).expect("Read test output"),
+      "p { color: blue; }"
+    );
+    assert!(fs::read_to_string(output_path.join("page.html"))
+      .expect("Read test output")
+      .contains("Second"));
+
+    fs::remove_file(input_path.join("styles.css")).expect("Delete test file");
+    let changes = watch::Changes {
+      modified: Vec::new(),
+      removed: vec![input_path.join("styles.css")],
+    };
+    assert_eq!(
+      rebuild_changed_files(&settings, &changes, &mut PageIncludes::new()).removed,
+      1
+    );
+    assert!(!output_path.join("styles.css").exists());
 
-    let expected_output = HTML_TEMPLATE
-      .replace("{{title}}", "html_template_test")
-      .replace("{{lang}}", "en")
-      + "\t<p>\n\t\ttest\n\n\t</p>\n</body>\n</html>\n";
-    let test_output_file_path = temp_dir.path().join("html_template_test.html");
-    let mut test_output_file = File::open(&test_output_file_path).unwrap();
-    let mut converted_string = String::new();
-    test_output_file
-      .read_to_string(&mut converted_string)
-      .expect("Read test output file");
+    temp_dir.close().expect("Delete test directory");
+  }
 
-    assert_eq!(converted_string, expected_output);
+  #[test]
+  fn converts_pages_including_changed_files() {
+    let temp_dir = tempfile::tempdir().expect("Create test directory");
+    let input_path = temp_dir.path().join("content");
+    let snippets_path = temp_dir.path().join("snippets");
+    let output_path = temp_dir.path().join("dist");
+    fs::create_dir_all(&input_path).expect("Create test directory");
+    fs::create_dir_all(&snippets_path).expect("Create test directory");
+    fs::write(snippets_path.join("footer.md"), "Old footer\n").expect("Create test file");
+    fs::write(input_path.join("page.md"), "{{ include \"../snippets/footer.md\" }}\n").expect("Create test file");
+    fs::write(input_path.join("other.md"), "No includes\n").expect("Create test file");
+    let settings = BuildSettings {
+      input: input_path.to_string_lossy().to_string(),
+      output_dir_path: output_path.to_string_lossy().to_string(),
+      template_path: None,
+      shortcodes_dir_path: None,
+      options: rost_gen::ConversionOptions::default(),
+    };
+    rost_gen::build(&settings).expect("Build test site");
+    let mut includes = page_includes(&settings.input);
+    let footer_path = snippets_path.join("footer.md").canonicalize().unwrap();
+    assert_eq!(includes[&input_path.join("page.md")], vec![footer_path.clone()]);
+
+    fs::write(snippets_path.join("footer.md"), "New footer\n").expect("Update test file");
+    let changes = watch::Changes {
+      modified: vec![footer_path],
+      removed: Vec::new(),
+    };
+    assert_eq!(rebuild_changed_files(&settings, &changes, &mut includes).converted, 1);
+    assert!(fs::read_to_string(output_path.join("page.html"))
+      .expect("Read test output")
+      .contains("New footer"));
 
-    drop(test_input_file);
     temp_dir.close().expect("Delete test directory");
   }
 
   #[test]
-  fn creates_html_in_directory() {
-    let temp_dir = tempfile::tempdir().unwrap();
-    let test_input_path1 = temp_dir.path().join("html_template_test1.txt");
-    let test_input_path2 = temp_dir.path().join("html_template_test2.txt");
-    let mut test_input_file1 = File::create(&test_input_path1).unwrap();
-    let mut test_input_file2 = File::create(&test_input_path2).unwrap();
-    writeln!(test_input_file1, "test1").expect("Create test input file");
-    writeln!(test_input_file2, "test2").expect("Create test input file");
-
-    let out_dir = "./out".to_owned();
-    fs::create_dir_all(&out_dir).expect("Create test output directory");
-
-    let input_dir = fs::read_dir(&temp_dir).expect("Read input directory");
-    convert_files_in_directory(input_dir, &out_dir, "en");
-
-    let expected_output1 = HTML_TEMPLATE
-      .replace("{{title}}", "html_template_test1")
-      .replace("{{lang}}", "en")
-      + "\t<p>\n\t\ttest1\n\n\t</p>\n</body>\n</html>\n";
-    let expected_output2 = HTML_TEMPLATE
-      .replace("{{title}}", "html_template_test2")
-      .replace("{{lang}}", "en")
-      + "\t<p>\n\t\ttest2\n\n\t</p>\n</body>\n</html>\n";
-
-    let output_file1 = "./out/html_template_test1.html";
-    let output_file2 = "./out/html_template_test2.html";
-    let mut test_output_file1 = File::open(output_file1).unwrap();
-    let mut test_output_file2 = File::open(output_file2).unwrap();
-    let mut converted_string1 = String::new();
-    let mut converted_string2 = String::new();
-    test_output_file1
-      .read_to_string(&mut converted_string1)
-      .expect("Read test output file");
-    test_output_file2
-      .read_to_string(&mut converted_string2)
-      .expect("Read test output file");
-
-    assert_eq!(converted_string1, expected_output1);
-    assert_eq!(converted_string2, expected_output2);
-
-    fs::remove_dir_all(&out_dir).expect("Remove output directory");
-    drop(test_input_file1);
-    drop(test_input_file2);
-    temp_dir.close().expect("Delete test directory");
+  fn keeps_only_flags_given_on_command_line() {
+    let matches = Args::command().get_matches_from(["rost_gen", "-c", "site.json", "build", "-l", "fr", "--reflow"]);
+    let args = Args::from_arg_matches(&matches).unwrap();
+    let values = command_line_values(&args, &matches);
+
+    assert_eq!(values.get("lang"), Some(&Value::from("fr")));
+    assert_eq!(values.get("reflow"), Some(&Value::from(true)));
+    assert_eq!(values.get("output"), None);
+    assert_eq!(values.get("chapters"), None);
   }
 }
@@ -0,0 +1,439 @@
+// Identifiers written with a command e.g. \alpha
+const IDENTIFIERS: [(&str, &str); 41] = [
+  ("alpha", "α"),
+  ("beta", "β"),
+  ("gamma", "γ"),
+  ("delta", "δ"),
+  ("epsilon", "ϵ"),
+  ("varepsilon", "ε"),
+  ("zeta", "ζ"),
+  ("eta", "η"),
+  ("theta", "θ"),
+  ("vartheta", "ϑ"),
+  ("iota", "ι"),
+  ("kappa", "κ"),
+  ("lambda", "λ"),
+  ("mu", "μ"),
+  ("nu", "ν"),
+  ("xi", "ξ"),
+  ("pi", "π"),
+  ("rho", "ρ"),
+  ("sigma", "σ"),
+  ("tau", "τ"),
+  ("upsilon", "υ"),
+  ("phi", "ϕ"),
+  ("varphi", "φ"),
+  ("chi", "χ"),
+  ("psi", "ψ"),
+  ("omega", "ω"),
+  ("Gamma", "Γ"),
+  ("Delta", "Δ"),
+  ("Theta", "Θ"),
+  ("Lambda", "Λ"),
+  ("Xi", "Ξ"),
+  ("Pi", "Π"),
+  ("Sigma", "Σ"),
+  ("Phi", "Φ"),
+  ("Psi", "Ψ"),
+  ("Omega", "Ω"),
+  ("infty", "∞"),
+  ("partial", "∂"),
+  ("nabla", "∇"),
+  ("emptyset", "∅"),
+  ("ell", "ℓ"),
+];
+// Operators, relations, arrows and delimiters written with a command
+const OPERATORS: [(&str, &str); 60] = [
+  ("pm", "±"),
+  ("mp", "∓"),
+  ("times", "×"),
+  ("div", "÷"),
+  ("cdot", "⋅"),
+  ("ast", "∗"),
+  ("circ", "∘"),
+  ("bullet", "∙"),
+  ("leq", "≤"),
+  ("le", "≤"),
+  ("geq", "≥"),
+  ("ge", "≥"),
+  ("neq", "≠"),
+  ("ne", "≠"),
+  ("ll", "≪"),
+  ("gg", "≫"),
+  ("approx", "≈"),
+  ("equiv", "≡"),
+  ("sim", "∼"),
+  ("simeq", "≃"),
+  ("cong", "≅"),
+  ("propto", "∝"),
+  ("to", "→"),
+  ("rightarrow", "→"),
+  ("leftarrow", "←"),
+  ("leftrightarrow", "↔"),
+  ("Rightarrow", "⇒"),
+  ("Leftarrow", "⇐"),
+  ("Leftrightarrow", "⇔"),
+  ("implies", "⟹"),
+  ("iff", "⟺"),
+  ("mapsto", "↦"),
+  ("in", "∈"),
+  ("notin", "∉"),
+  ("ni", "∋"),
+  ("subset", "⊂"),
+  ("subseteq", "⊆"),
+  ("supset", "⊃"),
+  ("supseteq", "⊇"),
+  ("cup", "∪"),
+  ("cap", "∩"),
+  ("setminus", "∖"),
+  ("forall", "∀"),
+  ("exists", "∃"),
+  ("neg", "¬"),
+  ("land", "∧"),
+  ("lor", "∨"),
+  ("mid", "∣"),
+  ("parallel", "∥"),
+  ("perp", "⊥"),
+  ("ldots", "…"),
+  ("cdots", "⋯"),
+  ("sum", "∑"),
+  ("prod", "∏"),
+  ("int", "∫"),
+  ("oint", "∮"),
+  ("langle", "⟨"),
+  ("rangle", "⟩"),
+  ("lfloor", "⌊"),
+  ("rfloor", "⌋"),
+];
+// Function names, written upright
+const FUNCTIONS: [&str; 26] = [
+  "sin", "cos", "tan", "cot", "sec", "csc", "arcsin", "arccos", "arctan", "sinh", "cosh", "tanh", "log", "ln", "lg",
+  "exp", "lim", "max", "min", "sup", "inf", "det", "gcd", "dim", "ker", "arg",
+];
+// Accents over their argument e.g. \hat{x}
+const ACCENTS: [(&str, &str); 6] = [
+  ("hat", "^"),
+  ("bar", "¯"),
+  ("overline", "¯"),
+  ("vec", "→"),
+  ("dot", "˙"),
+  ("tilde", "~"),
+];
+// Widths of spacing commands e.g. \, or \quad
+const SPACES: [(&str, &str); 7] = [
+  (",", "0.167em"),
+  (":", "0.222em"),
+  (";", "0.278em"),
+  ("!", "-0.167em"),
+  (" ", "0.25em"),
+  ("quad", "1em"),
+  ("qquad", "2em"),
+];
+// Font commands and the mathvariant of the text in them
+const FONTS: [(&str, &str); 5] = [
+  ("mathrm", "normal"),
+  ("mathbf", "bold"),
+  ("mathit", "italic"),
+  ("mathbb", "double-struck"),
+  ("mathcal", "script"),
+];
+
+/// Convert TeX math into a MathML `<math>` element
+/// returns a description of the problem if it uses TeX that isn't supported
+pub fn to_mathml(tex: &str, display: bool) -> Result<String, String> {
+  let mut parser = Parser {
+    chars: tex.chars().collect(),
+    position: 0,
+  };
+  let nodes = parser.parse_row(false)?;
+  let display = if display { " display=\"block\"" } else { "" };
+  Ok(format!("<math{display}>{}</math>", nodes.concat()))
+}
+
+struct Parser {
+  chars: Vec<char>,
+  position: usize,
+}
+
+impl Parser {
+  fn peek(&self) -> Option<char> { self.chars.get(self.position).copied() }
+
+  fn next(&mut self) -> Option<char> {
+    let char = self.peek();
+    self.position += 1;
+    char
+  }
+
+  fn skip_whitespace(&mut self) {
+    while self.peek().is_some_and(char::is_whitespace) {
+      self.position += 1;
+    }
+  }
+
+  // Nodes up to the end of the math, or the closing brace of a group
+  fn parse_row(&mut self, in_group: bool) -> Result<Vec<String>, String> {
+    let mut nodes = Vec::new();
+    loop {
+      self.skip_whitespace();
+      match self.peek() {
+        None if in_group => return Err("missing '}'".to_string()),
+        None => return Ok(nodes),
+        Some('}') if in_group => {
+          self.position += 1;
+          return Ok(nodes);
+        }
+        Some('}') => return Err("unexpected '}'".to_string()),
+        Some('^' | '_') => {
+          let base = nodes.pop().unwrap_or_else(|| "<mrow></mrow>".to_string());
+          nodes.push(self.parse_scripts(base)?);
+        }
+        Some(_) => nodes.push(self.parse_atom()?),
+      }
+    }
+  }
+
+  // Subscripts and superscripts after a base, in either order
+  fn parse_scripts(&mut self, base: String) -> Result<String, String> {
+    let mut subscript = None;
+    let mut superscript = None;
+    loop {
+      self.skip_whitespace();
+      let script = match self.peek() {
+        Some('_') => &mut subscript,
+        Some('^') => &mut superscript,
+        _ => break,
+      };
+      if script.is_some() {
+        return Err(format!("double '{}'", self.peek().unwrap_or_default()));
+      }
+      self.position += 1;
+      *script = Some(self.parse_argument()?);
+    }
+
+    Ok(match (subscript, superscript) {
+      (Some(subscript), Some(superscript)) => format!("<msubsup>{base}{subscript}{superscript}</msubsup>"),
+      (Some(subscript), None) => format!("<msub>{base}{subscript}</msub>"),
+      (None, Some(superscript)) => format!("<msup>{base}{superscript}</msup>"),
+      (None, None) => base,
+    })
+  }
+
+  // A group in braces, or a single character or command
+  fn parse_argument(&mut self) -> Result<String, String> {
+    self.skip_whitespace();
+    match self.peek() {
+      None | Some('}') => Err("missing argument".to_string()),
+      Some('^' | '_') => Err(format!("unexpected '{}'", self.peek().unwrap_or_default())),
+      Some(_) => self.parse_atom(),
+    }
+  }
+
+  fn parse_atom(&mut self) -> Result<String, String> {
+    let char = self.next().ok_or("missing argument")?;
+    match char {
+      '{' => Ok(mrow(self.parse_row(true)?)),
+      '\\' => self.parse_command(),
+      '0'..='9' => {
+        let mut number = char.to_string();
+        while let Some(digit) = self.peek().filter(|char| char.is_ascii_digit() || *char == '.') {
+          number.push(digit);
+          self.position += 1;
+        }
+        Ok(format!("<mn>{number}</mn>"))
+      }
+      '-' => Ok(mo("−")),
+      '*' => Ok(mo("∗")),
+      '\'' => Ok(mo("′")),
+      '~' => Ok(mspace("0.25em")),
+      '&' | '#' | '%' | '$' => Err(format!("unexpected '{char}'")),
+      char if char.is_alphabetic() => Ok(format!("<mi>{char}</mi>")),
+      char if char.is_alphanumeric() => Ok(format!("<mn>{char}</mn>")),
+      char => Ok(mo(&char.to_string())),
+    }
+  }
+
+  // A command after its backslash
+  fn parse_command(&mut self) -> Result<String, String> {
+    let mut name = String::new();
+    while let Some(letter) = self.peek().filter(char::is_ascii_alphabetic) {
+      name.push(letter);
+      self.position += 1;
+    }
+    if name.is_empty() {
+      name.push(self.next().ok_or("missing command after '\\'")?);
+    }
+
+    if let Some((_, identifier)) = IDENTIFIERS.iter().find(|(command, _)| *command == name) {
+      // Capital greek letters are upright, like in TeX
+      return Ok(if identifier.chars().next().is_some_and(char::is_uppercase) {
+        format!("<mi mathvariant=\"normal\">{identifier}</mi>")
+      } else {
+        format!("<mi>{identifier}</mi>")
+      });
+    }
+    if let Some((_, operator)) = OPERATORS.iter().find(|(command, _)| *command == name) {
+      return Ok(mo(operator));
+    }
+    if FUNCTIONS.contains(&name.as_str()) {
+      return Ok(format!("<mi>{name}</mi>"));
+    }
+    if let Some((_, width)) = SPACES.iter().find(|(command, _)| *command == name) {
+      return Ok(mspace(width));
+    }
+    if let Some((_, accent)) = ACCENTS.iter().find(|(command, _)| *command == name) {
+      let argument = self.parse_argument()?;
+      return Ok(format!("<mover accent=\"true\">{argument}{}</mover>", mo(accent)));
+    }
+    if let Some((_, variant)) = FONTS.iter().find(|(command, _)| *command == name) {
+      let text = self.parse_text_argument()?;
+      return Ok(format!("<mi mathvariant=\"{variant}\">{}</mi>", escape(&text)));
+    }
+
+    match name.as_str() {
+      "{" | "}" | "|" | "%" | "$" | "#" | "&" | "_" => Ok(mo(&name)),
+      "frac" | "dfrac" | "tfrac" => {
+        let numerator = self.parse_argument()?;
+        let denominator = self.parse_argument()?;
+        Ok(format!("<mfrac>{numerator}{denominator}</mfrac>"))
+      }
+      "binom" => {
+        let top = self.parse_argument()?;
+        let bottom = self.parse_argument()?;
+        Ok(format!(
+          "<mrow>{}<mfrac linethickness=\"0\">{top}{bottom}</mfrac>{}</mrow>",
+          mo("("),
+          mo(")")
+        ))
+      }
+      "sqrt" => {
+        self.skip_whitespace();
+        let index = if self.peek() == Some('[') {
+          self.position += 1;
+          Some(self.parse_index()?)
+        } else {
+          None
+        };
+        let radicand = self.parse_argument()?;
+        Ok(match index {
+          Some(index) => format!("<mroot>{radicand}{index}</mroot>"),
+          None => format!("<msqrt>{radicand}</msqrt>"),
+        })
+      }
+      "text" | "textrm" | "mbox" | "operatorname" => {
+        let text = self.parse_text_argument()?;
+        Ok(if name == "operatorname" {
+          format!("<mi>{}</mi>", escape(&text))
+        } else {
+          format!("<mtext>{}</mtext>", escape(&text))
+        })
+      }
+      // The delimiter after \left or \right, where . means no delimiter
+      "left" | "right" => {
+        self.skip_whitespace();
+        if self.peek() == Some('.') {
+          self.position += 1;
+          Ok(String::new())
+        } else {
+          self.parse_argument()
+        }
+      }
+      "\\" => Err("line breaks aren't supported".to_string()),
+      _ => Err(format!("unknown command '\\{name}'")),
+    }
+  }
+
+  // The index of a root, up to the closing ]
+  fn parse_index(&mut self) -> Result<String, String> {
+    let mut nodes = Vec::new();
+    loop {
+      self.skip_whitespace();
+      match self.peek() {
+        None => return Err("missing ']'".to_string()),
+        Some(']') => {
+          self.position += 1;
+          return Ok(mrow(nodes));
+        }
+        Some(_) => nodes.push(self.parse_atom()?),
+      }
+    }
+  }
+
+  // The text in braces after a command like \text, kept as written
+  fn parse_text_argument(&mut self) -> Result<String, String> {
+    self.skip_whitespace();
+    if self.next() != Some('{') {
+      return Err("missing '{' after text command".to_string());
+    }
+    let mut text = String::new();
+    let mut depth = 0;
+    loop {
+      match self.next() {
+        None => return Err("missing '}'".to_string()),
+        Some('}') if depth == 0 => return Ok(text),
+        Some(char) => {
+          match char {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            _ => {}
+          }
+          text.push(char);
+        }
+      }
+    }
+  }
+}
+
+// A single node, or several grouped so they're used as one argument
+fn mrow(nodes: Vec<String>) -> String {
+  if nodes.len() == 1 {
+    nodes.concat()
+  } else {
+    format!("<mrow>{}</mrow>", nodes.concat())
+  }
+}
+
+fn mo(operator: &str) -> String { format!("<mo>{}</mo>", escape(operator)) }
+
+fn mspace(width: &str) -> String { format!("<mspace width=\"{width}\"/>") }
+
+fn escape(text: &str) -> String { text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;") }
+
+#[cfg(test)]
+mod tests {
+  use crate::math::*;
+
+  #[test]
+  fn converts_scripts_and_fractions() {
+    assert_eq!(
+      to_mathml("x^2 + y_i^{n-1}", false).unwrap(),
+      "<math><msup><mi>x</mi><mn>2</mn></msup><mo>+</mo><msubsup><mi>y</mi><mi>i</mi><mrow><mi>n</mi><mo>−</mo><mn>1</\
+       mn></mrow></msubsup></math>"
+    );
+    assert_eq!(
+      to_mathml("\\frac{1}{\\sqrt[3]{x}} \\leq \\alpha", true).unwrap(),
+      "<math display=\"block\"><mfrac><mn>1</mn><mroot><mi>x</mi><mn>3</mn></mroot></mfrac><mo>≤</mo><mi>α</mi></math>"
+    );
+  }
+
+  #[test]
+  fn converts_text_functions_and_delimiters() {
+    assert_eq!(
+      to_mathml("\\sin\\left(\\theta\\right) \\text{ if } a<b", false).unwrap(),
+      "<math><mi>sin</mi><mo>(</mo><mi>θ</mi><mo>)</mo><mtext> if </mtext><mi>a</mi><mo>&lt;</mo><mi>b</mi></math>"
+    );
+    assert_eq!(
+      to_mathml("\\mathbb{R}\\,\\hat{x}", false).unwrap(),
+      "<math><mi mathvariant=\"double-struck\">R</mi><mspace width=\"0.167em\"/><mover \
+       accent=\"true\"><mi>x</mi><mo>^</mo></mover></math>"
+    );
+  }
+
+  #[test]
+  fn describes_unsupported_tex() {
+    assert_eq!(to_mathml("\\foo{x}", false), Err("unknown command '\\foo'".to_string()));
+    assert_eq!(to_mathml("\\frac{1}{2", false), Err("missing '}'".to_string()));
+    assert_eq!(to_mathml("x}", false), Err("unexpected '}'".to_string()));
+    assert_eq!(to_mathml("x^", false), Err("missing argument".to_string()));
+    assert_eq!(to_mathml("x^2^3", false), Err("double '^'".to_string()));
+  }
+}
@@ -0,0 +1,84 @@
+// Lines shorter than this fraction of the wrap width are assumed to be broken
+// on purpose, like in verse or the address lines of a letter
+const SHORT_LINE_RATIO: f64 = 0.6;
+
+/// The width text was hard-wrapped at, taken as its longest line
+pub fn wrap_width(lines: &[&str]) -> usize {
+  lines
+    .iter()
+    .map(|line| line.trim().chars().count())
+    .max()
+    .unwrap_or_default()
+}
+
+/// Join the lines of a paragraph into flowing text, keeping a line break
+/// after lines that are much shorter than the wrap width or are followed by an
+/// indented line
+/// returns the lines of text that should be separated by line breaks
+pub fn reflow_paragraph(lines: &[&str], wrap_width: usize) -> Vec<String> {
+  let lines: Vec<&str> = lines.iter().copied().filter(|line| !line.trim().is_empty()).collect();
+  let mut reflowed_lines = Vec::new();
+  let mut text = String::new();
+
+  for (i, line) in lines.iter().enumerate() {
+    let trimmed_line = line.trim();
+    text.push_str(trimmed_line);
+
+    if let Some(next_line) = lines.get(i + 1) {
+      let next_line_indented = next_line.starts_with([' ', '\t']);
+      let short_line = (trimmed_line.chars().count() as f64) < wrap_width as f64 * SHORT_LINE_RATIO;
+
+      if next_line_indented || short_line {
+        reflowed_lines.push(std::mem::take(&mut text));
+      } else {
+        text.push(' ');
+      }
+    }
+  }
+
+  if !text.is_empty() {
+    reflowed_lines.push(text);
+  }
+  reflowed_lines
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::reflow::*;
+
+  #[test]
+  fn joins_hard_wrapped_lines() {
+    let lines = [
+      "I was not surprised. Indeed, my only wonder was that he had not\n",
+      "already been mixed up in this extraordinary case, which was the\n",
+      "one topic of conversation.\n",
+    ];
+    let expected_output = "I was not surprised. Indeed, my only wonder was that he had not already been mixed up in \
+                           this extraordinary case, which was the one topic of conversation.";
+    assert_eq!(reflow_paragraph(&lines, wrap_width(&lines)), [expected_output]);
+  }
+
+  #[test]
+  fn keeps_short_lines() {
+    let lines = ["Dear Mr. Holmes,\n", "I am writing to you in the greatest distress.\n"];
+    assert_eq!(
+      reflow_paragraph(&lines, 70),
+      ["Dear Mr. Holmes,", "I am writing to you in the greatest distress."]
+    );
+  }
+
+  #[test]
+  fn keeps_lines_before_indented_lines() {
+    let lines = [
+      "The woods are lovely, dark and deep, but I have promises to keep,\n",
+      "    And miles to go before I sleep, and miles to go before I sleep.\n",
+    ];
+    assert_eq!(
+      reflow_paragraph(&lines, 70),
+      [
+        "The woods are lovely, dark and deep, but I have promises to keep,",
+        "And miles to go before I sleep, and miles to go before I sleep."
+      ]
+    );
+  }
+}
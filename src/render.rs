@@ -0,0 +1,623 @@
+use serde::{Deserialize, Serialize};
+
+use crate::document::{Admonition, Block, DefinitionItem, Document, Heading, Inline, Shortcode};
+use crate::{
+  emoji, escape_html_attribute, headings, math, shortcodes, typography, ConversionOptions, Format, HTML_TEMPLATE,
+};
+
+/// The kinds of files pages can be written as
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+  #[default]
+  Html,
+  Text,
+  /// The parsed document tree, for other tools to read
+  Json,
+}
+
+impl OutputFormat {
+  pub fn renderer(self) -> Box<dyn Renderer> {
+    match self {
+      OutputFormat::Html => Box::new(HtmlRenderer),
+      OutputFormat::Text => Box::new(TextRenderer),
+      OutputFormat::Json => Box::new(JsonRenderer),
+    }
+  }
+
+  /// Extension of the files written in this format, without the dot
+  pub fn extension(self) -> &'static str {
+    match self {
+      OutputFormat::Html => "html",
+      OutputFormat::Text => "txt",
+      OutputFormat::Json => "json",
+    }
+  }
+}
+
+/// The page being rendered and the options it's rendered with
+pub struct RenderContext<'a> {
+  pub document: &'a Document,
+  pub options: &'a ConversionOptions,
+  /// Name of the page's output file without its extension, if it's written to
+  /// a file
+  pub page_name: Option<&'a str>,
+}
+
+/// Turns a document into the contents of an output file
+///
+/// Every method has a default that writes html, so a renderer only needs to
+/// override the nodes it writes differently, e.g. just
+/// [`Renderer::render_link`] to rewrite links
+pub trait Renderer {
+  /// The whole page, filling in the page template
+  fn render_document(&self, context: &RenderContext) -> String {
+    let metadata = &context.document.metadata;
+    let options = context.options;
+
+    // Replace lang and title in the template with appropriate values
+    // If title was not found, page name will be used instead
+    let mut page_title = match &metadata.title {
+      Some(title) if metadata.emoji => emoji::replace_shortcodes(title),
+      Some(title) => title.clone(),
+      None => context.page_name.unwrap_or_default().to_string(),
+    };
+    if metadata.title.is_some() && metadata.smart_typography {
+      page_title = typography::smarten(&page_title, &options.lang);
+    }
+    // The default template has always had a line break after the page's own
+    // title
+    let html_template = match &options.template {
+      Some(template) => options.site.fill_template(template).replace("{{title}}", &page_title),
+      None if metadata.title.is_some() => HTML_TEMPLATE.replace("{{title}}", &format!("{page_title}\n")),
+      None => HTML_TEMPLATE.replace("{{title}}", &page_title),
+    }
+    .replace("{{lang}}", &options.lang);
+    // Custom templates add site metadata with {{site.*}} placeholders, so only
+    // the page author is added to them
+    let meta_tags = match &options.template {
+      Some(_) => metadata
+        .author
+        .as_ref()
+        .map(|author| {
+          format!(
+            "\t<meta name=\"author\" content=\"{}\">\n",
+            escape_html_attribute(author)
+          )
+        })
+        .unwrap_or_default(),
+      None => options.site.meta_tags(
+        context
+          .page_name
+          .map(|page_name| format!("{page_name}.html"))
+          .as_deref(),
+        metadata.author.as_deref(),
+      ),
+    };
+    let html_template = html_template.replace("</head>", &format!("{meta_tags}</head>"));
+
+    let mut page_content = self.render_body(context);
+    if metadata.emoji {
+      page_content = emoji::replace_shortcodes(&page_content);
+    }
+    if metadata.smart_typography {
+      page_content = typography::smarten(&page_content, &options.lang);
+    }
+
+    if options.template.is_some() {
+      html_template.replace("{{content}}", &page_content)
+    } else {
+      // Write closing tags for html file
+      format!("{html_template}{page_content}</body>\n</html>\n")
+    }
+  }
+
+  /// The title and blocks of the page, without the template
+  ///
+  /// The blocks are written inside paragraph tags that blank lines close and
+  /// open again, after any table of contents the parser added
+  fn render_body(&self, context: &RenderContext) -> String {
+    let document = context.document;
+    let mut body = String::new();
+    if let Some(title) = &document.title {
+      body.push_str(&self.render_title(title, context));
+    }
+    let mut blocks = document.blocks.as_slice();
+    if let [Block::TableOfContents { span }, rest @ ..] = blocks {
+      if span.start == span.end {
+        body.push_str(&self.render_toc(context));
+        blocks = rest;
+      }
+    }
+
+    body.push_str("\t<p>\n");
+    for block in blocks {
+      body.push_str(&self.render_block(block, context));
+    }
+    // The last paragraph tag is closed after a blank line, unless the page's
+    // last line has no line break of its own
+    if document.ends_with_newline || !matches!(blocks.last(), Some(Block::Paragraph { .. })) {
+      body.push('\n');
+    }
+    body.push_str("\t</p>\n");
+    body
+  }
+
+  fn render_block(&self, block: &Block, context: &RenderContext) -> String {
+    match block {
+      Block::Heading(heading) => self.render_heading(heading, context),
+      Block::Paragraph { content, .. } => self.render_paragraph(content, context),
+      Block::BlankLine { .. } => self.render_blank_line(context),
+      Block::ThematicBreak { .. } => self.render_thematic_break(context),
+      Block::TableOfContents { .. } => self.render_toc(context),
+      Block::Shortcode(shortcode) => self.render_shortcode(shortcode, context),
+      Block::Admonition(admonition) => self.render_admonition(admonition, context),
+      Block::Math { tex, .. } => self.render_math_block(tex, context),
+      Block::DefinitionList { items, .. } => self.render_definition_list(items, context),
+    }
+  }
+
+  /// The page's main heading, above the paragraphs of the page
+  fn render_title(&self, title: &Heading, context: &RenderContext) -> String {
+    let id = &title.id;
+    format!(
+      "\t<h1 id=\"{id}\">\n\t\t{}{}\n\t</h1>\n",
+      self.render_inlines(&title.content, context),
+      permalink(id, context)
+    )
+  }
+
+  /// Chapter headings in text close the paragraph they're in, and markdown
+  /// headings are written inside it
+  fn render_heading(&self, heading: &Heading, context: &RenderContext) -> String {
+    let level = heading.level;
+    let id = &heading.id;
+    let text = self.render_inlines(&heading.content, context);
+    let permalink = permalink(id, context);
+    match context.document.format {
+      Format::Text => format!("\t</p>\n\t<h{level} id=\"{id}\">\n\t\t{text}{permalink}\n\t</h{level}>\n\t<p>\n"),
+      Format::Markdown => format!("<h{level} id=\"{id}\">\n{text}{permalink}\n</h{level}>\n"),
+    }
+  }
+
+  /// The lines of a paragraph, inside the paragraph tag blank lines open
+  fn render_paragraph(&self, content: &[Inline], context: &RenderContext) -> String {
+    format!("\t\t{}\n", self.render_inlines(content, context))
+  }
+
+  /// Closes the paragraph tag and opens the next one, which in markdown
+  /// starts with the empty line itself
+  fn render_blank_line(&self, context: &RenderContext) -> String {
+    match context.document.format {
+      Format::Text => "\t</p>\n\t<p>\n".to_string(),
+      Format::Markdown => "\t</p>\n\t<p>\t\t\n".to_string(),
+    }
+  }
+
+  fn render_thematic_break(&self, _context: &RenderContext) -> String { "<hr />\n\n".to_string() }
+
+  /// A list of the page's headings, apart from the title
+  fn render_toc(&self, context: &RenderContext) -> String {
+    let toc_entries: Vec<headings::TocEntry> = context
+      .document
+      .headings()
+      .map(|heading| headings::TocEntry {
+        level: heading.level,
+        id: heading.id.clone(),
+        text: heading.text(),
+      })
+      .collect();
+    headings::render_toc(&toc_entries)
+  }
+
+  /// The shortcode's template filled in with its arguments and rendered body,
+  /// or just the body if there's no template for it
+  fn render_shortcode(&self, shortcode: &Shortcode, context: &RenderContext) -> String {
+    let body = shortcode
+      .body
+      .as_deref()
+      .map(|body| render_nested_blocks(self, body, context))
+      .unwrap_or_default();
+    match context.options.shortcodes.get(&shortcode.name) {
+      Some(template) => format!(
+        "\t</p>\n{}\n\t<p>\n",
+        shortcodes::expand(template, shortcode, &body).trim_end()
+      ),
+      None => format!("\t</p>\n{body}\t<p>\n"),
+    }
+  }
+
+  fn render_admonition(&self, admonition: &Admonition, context: &RenderContext) -> String {
+    let title = admonition
+      .title
+      .as_ref()
+      .map(|title| format!("\t<p class=\"admonition-title\">\n\t\t{title}\n\t</p>\n"))
+      .unwrap_or_default();
+    let body = render_nested_blocks(self, &admonition.body, context);
+    format!(
+      "\t</p>\n\t<aside class=\"admonition {}\">\n{title}{body}\t</aside>\n\t<p>\n",
+      admonition.kind
+    )
+  }
+
+  /// `$$` math on lines of its own, outside the paragraph tags
+  fn render_math_block(&self, tex: &str, context: &RenderContext) -> String {
+    format!("\t</p>\n\t{}\n\t<p>\n", self.render_math(tex, true, context))
+  }
+
+  fn render_definition_list(&self, items: &[DefinitionItem], context: &RenderContext) -> String {
+    let mut list = "\t</p>\n\t<dl>\n".to_string();
+    for item in items {
+      list.push_str(&format!("\t\t<dt>{}</dt>\n", self.render_inlines(&item.term, context)));
+      for definition in &item.definitions {
+        list.push_str(&format!("\t\t<dd>{}</dd>\n", self.render_inlines(definition, context)));
+      }
+    }
+    list.push_str("\t</dl>\n\t<p>\n");
+    list
+  }
+
+  fn render_inlines(&self, inlines: &[Inline], context: &RenderContext) -> String {
+    inlines
+      .iter()
+      .map(|inline| self.render_inline(inline, context))
+      .collect()
+  }
+
+  fn render_inline(&self, inline: &Inline, context: &RenderContext) -> String {
+    match inline {
+      Inline::Text(text) => self.render_text(text, context),
+      Inline::Link { text, url, .. } => self.render_link(text, url, context),
+      Inline::SoftBreak => self.render_soft_break(context),
+      Inline::LineBreak => self.render_line_break(context),
+      Inline::Math { tex, display, .. } => self.render_math(tex, *display, context),
+      Inline::Abbreviation { text, title } => self.render_abbreviation(text, title, context),
+      Inline::Superscript(text) => self.render_superscript(text, context),
+      Inline::Subscript(text) => self.render_subscript(text, context),
+    }
+  }
+
+  /// Text is written as is, so html in the source ends up in the page
+  fn render_text(&self, text: &str, _context: &RenderContext) -> String { text.to_string() }
+
+  fn render_link(&self, text: &str, url: &str, _context: &RenderContext) -> String {
+    format!("<a href=\"{url}\">{text}</a>")
+  }
+
+  fn render_soft_break(&self, _context: &RenderContext) -> String { "\n\t\t".to_string() }
+
+  fn render_abbreviation(&self, text: &str, title: &str, _context: &RenderContext) -> String {
+    format!("<abbr title=\"{}\">{text}</abbr>", escape_html_attribute(title))
+  }
+
+  fn render_superscript(&self, text: &str, _context: &RenderContext) -> String { format!("<sup>{text}</sup>") }
+
+  fn render_subscript(&self, text: &str, _context: &RenderContext) -> String { format!("<sub>{text}</sub>") }
+
+  /// MathML, or the TeX as written if it can't be converted
+  fn render_math(&self, tex: &str, display: bool, _context: &RenderContext) -> String {
+    math::to_mathml(tex, display)
+      .unwrap_or_else(|_| format!("<code class=\"math\">{}</code>", escape_html_attribute(tex)))
+  }
+
+  fn render_line_break(&self, _context: &RenderContext) -> String { "<br>\n\t\t".to_string() }
+}
+
+// The hover link to a heading, if permalinks are on
+fn permalink(id: &str, context: &RenderContext) -> String {
+  if context.options.permalinks {
+    format!(" <a class=\"anchor\" href=\"#{id}\" aria-label=\"Permalink\">#</a>")
+  } else {
+    String::new()
+  }
+}
+
+// The blocks in a shortcode or admonition, inside paragraph tags of their own
+fn render_nested_blocks<R: Renderer + ?Sized>(renderer: &R, blocks: &[Block], context: &RenderContext) -> String {
+  if blocks.is_empty() {
+    return String::new();
+  }
+  let blocks: String = blocks
+    .iter()
+    .map(|block| renderer.render_block(block, context))
+    .collect();
+  format!("\t<p>\n{blocks}\t</p>\n")
+}
+
+/// Writes html pages, using the default template or the one in the options
+pub struct HtmlRenderer;
+
+impl Renderer for HtmlRenderer {}
+
+/// Writes plain text, with headings underlined and each link followed by its
+/// url
+pub struct TextRenderer;
+
+impl Renderer for TextRenderer {
+  fn render_document(&self, context: &RenderContext) -> String {
+    let mut text = self.render_body(context);
+    if context.document.metadata.emoji {
+      text = emoji::replace_shortcodes(&text);
+    }
+    if context.document.metadata.smart_typography {
+      text = typography::smarten(&text, &context.options.lang);
+    }
+    format!("{}\n", text.trim_end())
+  }
+
+  fn render_body(&self, context: &RenderContext) -> String {
+    let mut body = String::new();
+    if let Some(title) = &context.document.title {
+      body.push_str(&self.render_heading(title, context));
+    }
+    for block in &context.document.blocks {
+      body.push_str(&self.render_block(block, context));
+    }
+    body
+  }
+
+  fn render_heading(&self, heading: &Heading, context: &RenderContext) -> String {
+    let text = self.render_inlines(&heading.content, context);
+    let underline = if heading.level == 1 { "=" } else { "-" };
+    format!("{text}\n{}\n\n", underline.repeat(text.chars().count()))
+  }
+
+  fn render_paragraph(&self, content: &[Inline], context: &RenderContext) -> String {
+    format!("{}\n\n", self.render_inlines(content, context))
+  }
+
+  // Paragraphs are already separated by an empty line
+  fn render_blank_line(&self, _context: &RenderContext) -> String { String::new() }
+
+  fn render_thematic_break(&self, _context: &RenderContext) -> String { "* * *\n\n".to_string() }
+
+  fn render_toc(&self, context: &RenderContext) -> String {
+    let mut toc = String::new();
+    for heading in context.document.headings() {
+      toc.push_str(&format!("{}- {}\n", "  ".repeat(heading.level - 1), heading.text()));
+    }
+    if !toc.is_empty() {
+      toc.push('\n');
+    }
+    toc
+  }
+
+  // Shortcode templates are html, so only the body is kept
+  fn render_shortcode(&self, shortcode: &Shortcode, context: &RenderContext) -> String {
+    shortcode
+      .body
+      .iter()
+      .flatten()
+      .map(|block| self.render_block(block, context))
+      .collect()
+  }
+
+  // e.g. WARNING: Title, followed by the body
+  fn render_admonition(&self, admonition: &Admonition, context: &RenderContext) -> String {
+    let label = match &admonition.title {
+      Some(title) => format!("{}: {title}", admonition.kind.to_uppercase()),
+      None => admonition.kind.to_uppercase(),
+    };
+    let body: String = admonition
+      .body
+      .iter()
+      .map(|block| self.render_block(block, context))
+      .collect();
+    format!("{label}\n\n{body}")
+  }
+
+  fn render_math_block(&self, tex: &str, context: &RenderContext) -> String {
+    format!("\t{}\n", self.render_math(tex, true, context))
+  }
+
+  // Each term followed by its indented definitions
+  fn render_definition_list(&self, items: &[DefinitionItem], context: &RenderContext) -> String {
+    let mut list = String::new();
+    for item in items {
+      list.push_str(&format!("{}\n", self.render_inlines(&item.term, context)));
+      for definition in &item.definitions {
+        list.push_str(&format!("    {}\n", self.render_inlines(definition, context)));
+      }
+    }
+    format!("{list}\n")
+  }
+
+  fn render_link(&self, text: &str, url: &str, _context: &RenderContext) -> String {
+    if url.is_empty() || url == text {
+      text.to_string()
+    } else {
+      format!("{text} ({url})")
+    }
+  }
+
+  fn render_soft_break(&self, _context: &RenderContext) -> String { "\n".to_string() }
+
+  fn render_math(&self, tex: &str, display: bool, _context: &RenderContext) -> String {
+    if display {
+      format!("$${tex}$$")
+    } else {
+      format!("${tex}$")
+    }
+  }
+
+  fn render_line_break(&self, _context: &RenderContext) -> String { "\n".to_string() }
+
+  fn render_abbreviation(&self, text: &str, _title: &str, _context: &RenderContext) -> String { text.to_string() }
+
+  fn render_superscript(&self, text: &str, _context: &RenderContext) -> String { format!("^{text}^") }
+
+  fn render_subscript(&self, text: &str, _context: &RenderContext) -> String { format!("~{text}~") }
+}
+
+/// Writes the document tree as JSON
+pub struct JsonRenderer;
+
+impl Renderer for JsonRenderer {
+  fn render_document(&self, context: &RenderContext) -> String {
+    let json = serde_json::to_string_pretty(context.document).expect("Serialize document");
+    format!("{json}\n")
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::document::parse;
+  use crate::render::*;
+  use crate::Format;
+
+  const SOURCE: &str = "# Notes\nSee [the docs](docs.html)\nfor more\n\n---\n";
+
+  fn render(renderer: &dyn Renderer, options: &ConversionOptions) -> String {
+    let document = parse(SOURCE, Format::Markdown, options);
+    renderer.render_body(&RenderContext {
+      document: &document,
+      options,
+      page_name: None,
+    })
+  }
+
+  #[test]
+  fn renders_html() {
+    assert_eq!(
+      render(&HtmlRenderer, &ConversionOptions::default()),
+      "\t<p>\n<h1 id=\"notes\">\nNotes\n</h1>\n\t\tSee <a href=\"docs.html\">the docs</a>\n\t\tfor \
+       more\n\t</p>\n\t<p>\t\t\n<hr />\n\n\n\t</p>\n"
+    );
+  }
+
+  #[test]
+  fn renders_plain_text() {
+    assert_eq!(
+      render(&TextRenderer, &ConversionOptions::default()),
+      "Notes\n=====\n\nSee the docs (docs.html)\nfor more\n\n* * *\n\n"
+    );
+  }
+
+  #[test]
+  fn renders_json_tree() {
+    let options = ConversionOptions::default();
+    let document = parse(SOURCE, Format::Markdown, &options);
+    let json = JsonRenderer.render_document(&RenderContext {
+      document: &document,
+      options: &options,
+      page_name: None,
+    });
+
+    let value: serde_json::Value = serde_json::from_str(&json).expect("Parse rendered json");
+    assert_eq!(value["blocks"][0]["heading"]["id"], "notes");
+    assert_eq!(
+      value["blocks"][1]["paragraph"]["content"][1]["link"]["url"],
+      "docs.html"
+    );
+    assert_eq!(value["blocks"][1]["paragraph"]["span"]["line"], 2);
+  }
+
+  #[test]
+  fn fills_in_shortcode_templates() {
+    let options = ConversionOptions {
+      shortcodes: std::collections::HashMap::from([(
+        "figure".to_string(),
+        "<figure>\n\t<img src=\"{{0}}\" alt=\"{{alt}}\">\n\t<figcaption>{{body}}</figcaption>\n</figure>".to_string(),
+      )]),
+      ..Default::default()
+    };
+    let document = parse(
+      "{% figure cat.png alt=\"A cat\" %}\nAsleep\n{% endfigure %}\n",
+      Format::Markdown,
+      &options,
+    );
+    let context = RenderContext {
+      document: &document,
+      options: &options,
+      page_name: None,
+    };
+
+    assert_eq!(
+      HtmlRenderer.render_body(&context),
+      "\t<p>\n\t</p>\n<figure>\n\t<img src=\"cat.png\" alt=\"A \
+       cat\">\n\t<figcaption>\t<p>\n\t\tAsleep\n\t</p>\n</figcaption>\n</figure>\n\t<p>\n\n\t</p>\n"
+    );
+    assert_eq!(TextRenderer.render_body(&context), "Asleep\n\n");
+  }
+
+  #[test]
+  fn renders_admonitions() {
+    let options = ConversionOptions::default();
+    let document = parse("> [!NOTE] Heads up\n> Read this\n", Format::Markdown, &options);
+    let context = RenderContext {
+      document: &document,
+      options: &options,
+      page_name: None,
+    };
+
+    assert_eq!(
+      HtmlRenderer.render_body(&context),
+      "\t<p>\n\t</p>\n\t<aside class=\"admonition note\">\n\t<p class=\"admonition-title\">\n\t\tHeads \
+       up\n\t</p>\n\t<p>\n\t\tRead this\n\t</p>\n\t</aside>\n\t<p>\n\n\t</p>\n"
+    );
+    assert_eq!(TextRenderer.render_body(&context), "NOTE: Heads up\n\nRead this\n\n");
+  }
+
+  #[test]
+  fn renders_math_as_mathml() {
+    let options = ConversionOptions::default();
+    let document = parse("Area $\\pi r^2$ or $\\nope$\n", Format::Markdown, &options);
+    let context = RenderContext {
+      document: &document,
+      options: &options,
+      page_name: None,
+    };
+
+    assert_eq!(
+      HtmlRenderer.render_body(&context),
+      "\t<p>\n\t\tArea <math><mi>π</mi><msup><mi>r</mi><mn>2</mn></msup></math> or <code \
+       class=\"math\">\\nope</code>\n\n\t</p>\n"
+    );
+    assert_eq!(TextRenderer.render_body(&context), "Area $\\pi r^2$ or $\\nope$\n\n");
+  }
+
+  #[test]
+  fn renders_definition_lists_and_abbreviations() {
+    let options = ConversionOptions {
+      definition_lists: true,
+      abbreviations: true,
+      superscript: true,
+      ..Default::default()
+    };
+    let document = parse(
+      "CSS\n: Styles \"pages\"\n: Level 3^rd^\n*[CSS]: Cascading \"Style\" Sheets\n",
+      Format::Markdown,
+      &options,
+    );
+    let context = RenderContext {
+      document: &document,
+      options: &options,
+      page_name: None,
+    };
+
+    assert_eq!(
+      HtmlRenderer.render_body(&context),
+      "\t<p>\n\t</p>\n\t<dl>\n\t\t<dt><abbr title=\"Cascading &quot;Style&quot; \
+       Sheets\">CSS</abbr></dt>\n\t\t<dd>Styles \"pages\"</dd>\n\t\t<dd>Level \
+       3<sup>rd</sup></dd>\n\t</dl>\n\t<p>\n\n\t</p>\n"
+    );
+    assert_eq!(
+      TextRenderer.render_body(&context),
+      "CSS\n    Styles \"pages\"\n    Level 3^rd^\n\n"
+    );
+  }
+
+  #[test]
+  fn overrides_single_nodes() {
+    struct SecureLinks;
+    impl Renderer for SecureLinks {
+      fn render_link(&self, text: &str, url: &str, _context: &RenderContext) -> String {
+        format!("<a href=\"https://example.com/{url}\">{text}</a>")
+      }
+    }
+
+    let html = render(&SecureLinks, &ConversionOptions::default());
+    assert!(html.contains("<a href=\"https://example.com/docs.html\">the docs</a>"));
+    assert!(html.contains("<h1 id=\"notes\">"));
+  }
+}
@@ -0,0 +1,169 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{fs, io, path};
+
+use rost_gen::headings::slugify;
+
+const CONFIG_FILE_NAME: &str = "rost_gen.json";
+const SITE_CONFIG: &str = "{\n  \"input\": \"content\",\n  \"output\": \"dist\",\n  \"lang\": \"en-CA\",\n  \
+                           \"template\": \"template.html\",\n  \"title\": \"My site\",\n  \"description\": \"A site \
+                           generated by rost_gen\",\n  \"nav\": [\n    { \"title\": \"Home\", \"url\": \"index.html\" \
+                           }\n  ]\n}\n";
+const SITE_TEMPLATE: &str =
+  "<!DOCTYPE html>\n<html lang=\"{{lang}}\">\n<head>\n\t<meta charset=\"UTF-8\">\n\t<meta name=\"viewport\" \
+   content=\"width=device-width, initial-scale=1.0\">\n\t<meta name=\"description\" \
+   content=\"{{site.description}}\">\n\t<title>{{title}} | {{site.title}}</title>\n\t<link rel=\"stylesheet\" \
+   href=\"styles.css\">\n</head>\n<body>\n{{site.nav}}\t<main>\n{{content}}\t</main>\n</body>\n</html>\n";
+const SAMPLE_PAGE: &str = "---\ntitle: Welcome\n---\nThis site was generated by rost_gen.\n\nEdit this page in \
+                           content/index.md, or add a new post with `rost_gen new \"My first post\"`.\n";
+pub const DEFAULT_STYLESHEET: &str =
+  "body {\n  font-family: system-ui, sans-serif;\n  line-height: 1.6;\n  color: #222;\n  margin: 0;\n}\n\nmain {\n  \
+   max-width: 45rem;\n  margin: 0 auto;\n  padding: 1rem;\n}\n\na {\n  color: #0b5cad;\n}\n\n.anchor {\n  visibility: \
+   hidden;\n  text-decoration: none;\n}\n\nh1:hover .anchor,\nh2:hover .anchor,\nh3:hover .anchor,\nh4:hover \
+   .anchor,\nh5:hover .anchor,\nh6:hover .anchor {\n  visibility: visible;\n}\n\n.toc {\n  border-left: 3px solid \
+   #ddd;\n  padding-left: 1rem;\n}\n\n.site-nav ul {\n  display: flex;\n  gap: 1rem;\n  list-style: none;\n  \
+   max-width: 45rem;\n  margin: 0 auto;\n  padding: 1rem;\n}\n\n.admonition {\n  border-left: 4px solid #0b5cad;\n  \
+   background: #eef4fb;\n  margin: 1rem 0;\n  padding: 0.5rem 1rem;\n}\n\n.admonition-title {\n  font-weight: bold;\n  \
+   margin: 0;\n}\n\n.admonition.tip {\n  border-color: #1a7f37;\n  background: #eef8f0;\n}\n\n.admonition.important \
+   {\n  border-color: #8250df;\n  background: #f5f0fd;\n}\n\n.admonition.warning {\n  border-color: #9a6700;\n  \
+   background: #fdf6e3;\n}\n\n.admonition.caution,\n.admonition.danger {\n  border-color: #cf222e;\n  background: \
+   #fdeeee;\n}\n";
+
+/// Create a config, template, sample page and stylesheet for a new site in
+/// the given directory, returning the paths of the created files
+pub fn init_site(site_dir: &path::Path) -> io::Result<Vec<path::PathBuf>> {
+  let files = [
+    (site_dir.join(CONFIG_FILE_NAME), SITE_CONFIG),
+    (site_dir.join("template.html"), SITE_TEMPLATE),
+    (site_dir.join("content").join("index.md"), SAMPLE_PAGE),
+    (site_dir.join("content").join("styles.css"), DEFAULT_STYLESHEET),
+  ];
+
+  // Don't overwrite anything from an existing site
+  if let Some((existing_path, _)) = files.iter().find(|(file_path, _)| file_path.exists()) {
+    return Err(io::Error::new(
+      io::ErrorKind::AlreadyExists,
+      format!("'{}' already exists", existing_path.display()),
+    ));
+  }
+
+  fs::create_dir_all(site_dir.join("content"))?;
+  for (file_path, contents) in &files {
+    fs::write(file_path, contents)?;
+  }
+
+  Ok(files.into_iter().map(|(file_path, _)| file_path).collect())
+}
+
+/// Create a markdown post named after today's date and the title, with the
+/// title and date in its front matter
+pub fn new_post(posts_dir: &path::Path, title: &str) -> io::Result<path::PathBuf> {
+  let date = today();
+  let post_path = posts_dir.join(format!("{date}-{}.md", slugify(title)));
+
+  if post_path.exists() {
+    return Err(io::Error::new(
+      io::ErrorKind::AlreadyExists,
+      format!("'{}' already exists", post_path.display()),
+    ));
+  }
+
+  fs::create_dir_all(posts_dir)?;
+  let quoted_title = title.replace('\\', "\\\\").replace('"', "\\\"");
+  fs::write(
+    &post_path,
+    format!("---\ntitle: \"{quoted_title}\"\ndate: {date}\n---\n"),
+  )?;
+  Ok(post_path)
+}
+
+// Today's date in UTC as YYYY-MM-DD
+fn today() -> String {
+  let days_since_epoch = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map_or(0, |duration| duration.as_secs() / 86_400);
+  let (year, month, day) = date_from_days(days_since_epoch as i64);
+  format!("{year:04}-{month:02}-{day:02}")
+}
+
+// Convert days since 1970-01-01 into a (year, month, day) date, see
+// http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn date_from_days(days_since_epoch: i64) -> (i64, i64, i64) {
+  let days = days_since_epoch + 719_468;
+  let era = days.div_euclid(146_097);
+  let day_of_era = days.rem_euclid(146_097);
+  let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+  let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+  let shifted_month = (5 * day_of_year + 2) / 153;
+  let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+  let month = if shifted_month < 10 {
+    shifted_month + 3
+  } else {
+    shifted_month - 9
+  };
+  let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+  (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::scaffold::*;
+  use rost_gen::{config, parse, ConversionOptions, Format};
+
+  #[test]
+  fn converts_days_to_dates() {
+    assert_eq!(date_from_days(0), (1970, 1, 1));
+    assert_eq!(date_from_days(11_016), (2000, 2, 29));
+    assert_eq!(date_from_days(19_723), (2024, 1, 1));
+  }
+
+  #[test]
+  fn creates_site_files() {
+    let temp_dir = tempfile::tempdir().unwrap();
+
+    let created_files = init_site(temp_dir.path()).unwrap();
+
+    assert_eq!(created_files.len(), 4);
+    assert!(created_files.iter().all(|file_path| file_path.is_file()));
+    assert!(fs::read_to_string(temp_dir.path().join("template.html"))
+      .unwrap()
+      .contains("{{content}}"));
+    let site_config = fs::read_to_string(temp_dir.path().join(CONFIG_FILE_NAME)).unwrap();
+    assert!(config::parse_config(&site_config, config::ConfigFormat::Json).is_ok());
+
+    temp_dir.close().expect("Delete test directory");
+  }
+
+  #[test]
+  fn does_not_overwrite_existing_site() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    fs::write(temp_dir.path().join(CONFIG_FILE_NAME), "{}").expect("Create test config");
+
+    assert!(init_site(temp_dir.path()).is_err());
+    assert_eq!(
+      fs::read_to_string(temp_dir.path().join(CONFIG_FILE_NAME)).unwrap(),
+      "{}"
+    );
+    assert!(!temp_dir.path().join("template.html").exists());
+
+    temp_dir.close().expect("Delete test directory");
+  }
+
+  #[test]
+  fn creates_dated_post_with_front_matter() {
+    let temp_dir = tempfile::tempdir().unwrap();
+
+    let post_path = new_post(&temp_dir.path().join("posts"), "My \"First\" Post").unwrap();
+    let file_name = post_path.file_name().unwrap().to_str().unwrap();
+    let contents = fs::read_to_string(&post_path).unwrap();
+
+    assert!(file_name.ends_with("-my-first-post.md"));
+    assert_eq!(&file_name[..10], today());
+    assert!(contents.starts_with("---\ntitle: \"My \\\"First\\\" Post\"\ndate: "));
+    let document = parse(&contents, Format::Markdown, &ConversionOptions::default());
+    assert_eq!(document.metadata.title.as_deref(), Some("My \"First\" Post"));
+    assert!(new_post(&temp_dir.path().join("posts"), "My \"First\" Post").is_err());
+
+    temp_dir.close().expect("Delete test directory");
+  }
+}
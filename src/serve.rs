@@ -0,0 +1,273 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::{fs, path, thread};
+
+use rost_gen::link_checker::percent_decode;
+
+// Polled by pages to find out when the site has been rebuilt
+const RELOAD_PATH: &str = "/__rost_gen/reload";
+const LIVE_RELOAD_SCRIPT: &str =
+  "<script>\n(function () {\n  var version = null;\n  setInterval(function () {\n    fetch(\"/__rost_gen/reload\", { \
+   cache: \"no-store\" })\n      .then(function (response) { return response.text(); })\n      .then(function \
+   (latest) {\n        if (version !== null && latest !== version) {\n          location.reload();\n        }\n        \
+   version = latest;\n      })\n      .catch(function () {});\n  }, 1000);\n})();\n</script>\n";
+const NOT_FOUND_HTML: &str = "<!DOCTYPE html>\n<html>\n<head>\n\t<title>404 Not \
+                              Found</title>\n</head>\n<body>\n\t<h1>404 Not Found</h1>\n</body>\n</html>\n";
+
+/// A response sent back to the browser
+struct Response {
+  status: u16,
+  content_type: &'static str,
+  body: Vec<u8>,
+}
+
+/// Serve the output directory on localhost in a background thread, returning
+/// the address it's listening on. Pages reload when `reload_version` changes
+pub fn start_server(root: path::PathBuf, port: u16, reload_version: Arc<AtomicU64>) -> io::Result<SocketAddr> {
+  let listener = TcpListener::bind(("127.0.0.1", port))?;
+  let address = listener.local_addr()?;
+
+  thread::spawn(move || {
+    for stream in listener.incoming().flatten() {
+      let root = root.clone();
+      let reload_version = Arc::clone(&reload_version);
+      thread::spawn(move || {
+        // The browser going away mid response isn't a problem for the server
+        let _ = handle_connection(stream, &root, &reload_version);
+      });
+    }
+  });
+
+  Ok(address)
+}
+
+fn handle_connection(mut stream: TcpStream, root: &path::Path, reload_version: &AtomicU64) -> io::Result<()> {
+  let mut reader = BufReader::new(stream.try_clone()?);
+  let mut request_line = String::new();
+  reader.read_line(&mut request_line)?;
+
+  // Skip the headers, none of them change the response
+  let mut header = String::new();
+  while reader.read_line(&mut header)? > 2 {
+    header.clear();
+  }
+
+  let mut request_parts = request_line.split_whitespace();
+  let method = request_parts.next().unwrap_or_default();
+  let target = request_parts.next().unwrap_or("/");
+
+  let response = match method {
+    "GET" | "HEAD" => respond(root, target, reload_version),
+    _ => Response {
+      status: 405,
+      content_type: "text/plain; charset=utf-8",
+      body: b"Method Not Allowed".to_vec(),
+    },
+  };
+
+  write!(
+    stream,
+    "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+    response.status,
+    reason_phrase(response.status),
+    response.content_type,
+    response.body.len()
+  )?;
+  if method != "HEAD" {
+    stream.write_all(&response.body)?;
+  }
+  stream.flush()
+}
+
+fn respond(root: &path::Path, target: &str, reload_version: &AtomicU64) -> Response {
+  let request_path = target.split(['?', '#']).next().unwrap_or_default();
+
+  if request_path == RELOAD_PATH {
+    return Response {
+      status: 200,
+      content_type: "text/plain; charset=utf-8",
+      body: reload_version.load(Ordering::SeqCst).to_string().into_bytes(),
+    };
+  }
+
+  match resolve_file(root, request_path) {
+    Some(file_path) => match fs::read(&file_path) {
+      Ok(contents) => file_response(200, &file_path, contents),
+      Err(_) => not_found(root),
+    },
+    None => not_found(root),
+  }
+}
+
+// Map a request path to a file inside the root, using index.html for
+// directories. Paths that would leave the root aren't served
+fn resolve_file(root: &path::Path, request_path: &str) -> Option<path::PathBuf> {
+  let mut file_path = root.to_path_buf();
+
+  for segment in percent_decode(request_path).split('/') {
+    match segment {
+      "" | "." => {}
+      ".." => return None,
+      segment if segment.contains('\\') => return None,
+      segment => file_path.push(segment),
+    }
+  }
+
+  if file_path.is_dir() {
+    file_path.push("index.html");
+  }
+
+  file_path.is_file().then_some(file_path)
+}
+
+// Use the site's own 404.html if it has one
+fn not_found(root: &path::Path) -> Response {
+  let custom_page_path = root.join("404.html");
+
+  match fs::read(&custom_page_path) {
+    Ok(contents) => file_response(404, &custom_page_path, contents),
+    Err(_) => file_response(404, &custom_page_path, NOT_FOUND_HTML.as_bytes().to_vec()),
+  }
+}
+
+fn file_response(status: u16, file_path: &path::Path, contents: Vec<u8>) -> Response {
+  let content_type = content_type(file_path);
+
+  let body = if content_type.starts_with("text/html") {
+    inject_live_reload(&String::from_utf8_lossy(&contents)).into_bytes()
+  } else {
+    contents
+  };
+
+  Response {
+    status,
+    content_type,
+    body,
+  }
+}
+
+fn inject_live_reload(html: &str) -> String {
+  match html.rfind("</body>") {
+    Some(body_end) => format!("{}{LIVE_RELOAD_SCRIPT}{}", &html[..body_end], &html[body_end..]),
+    None => format!("{html}{LIVE_RELOAD_SCRIPT}"),
+  }
+}
+
+fn content_type(file_path: &path::Path) -> &'static str {
+  let extension = file_path
+    .extension()
+    .and_then(|extension| extension.to_str())
+    .unwrap_or_default()
+    .to_lowercase();
+
+  match extension.as_str() {
+    "html" | "htm" => "text/html; charset=utf-8",
+    "css" => "text/css; charset=utf-8",
+    "js" | "mjs" => "text/javascript; charset=utf-8",
+    "json" => "application/json",
+    "txt" | "md" => "text/plain; charset=utf-8",
+    "xml" => "application/xml",
+    "svg" => "image/svg+xml",
+    "png" => "image/png",
+    "jpg" | "jpeg" => "image/jpeg",
+    "gif" => "image/gif",
+    "webp" => "image/webp",
+    "ico" => "image/x-icon",
+    "woff" => "font/woff",
+    "woff2" => "font/woff2",
+    "pdf" => "application/pdf",
+    _ => "application/octet-stream",
+  }
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+  match status {
+    200 => "OK",
+    404 => "Not Found",
+    405 => "Method Not Allowed",
+    _ => "",
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::io::Read;
+
+  use crate::serve::*;
+
+  fn request(address: SocketAddr, path: &str) -> String {
+    let mut stream = TcpStream::connect(address).expect("Connect to test server");
+    write!(stream, "GET {path} HTTP/1.1\r\nHost: 127.0.0.1\r\n\r\n").expect("Send test request");
+    let mut response = String::new();
+    stream.read_to_string(&mut response).expect("Read test response");
+    response
+  }
+
+  fn start_test_server() -> (tempfile::TempDir, SocketAddr, Arc<AtomicU64>) {
+    let temp_dir = tempfile::tempdir().unwrap();
+    fs::create_dir(temp_dir.path().join("posts")).expect("Create test directory");
+    fs::write(temp_dir.path().join("index.html"), "<html><body>Home</body></html>").expect("Create test file");
+    fs::write(temp_dir.path().join("posts/index.html"), "<p>Posts</p>").expect("Create test file");
+    fs::write(temp_dir.path().join("styles.css"), "body {}").expect("Create test file");
+
+    let reload_version = Arc::new(AtomicU64::new(0));
+    let address = start_server(temp_dir.path().to_path_buf(), 0, Arc::clone(&reload_version)).unwrap();
+    (temp_dir, address, reload_version)
+  }
+
+  #[test]
+  fn serves_files_with_mime_types() {
+    let (temp_dir, address, _) = start_test_server();
+
+    let response = request(address, "/styles.css");
+
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.contains("Content-Type: text/css; charset=utf-8\r\n"));
+    assert!(response.ends_with("\r\n\r\nbody {}"));
+
+    temp_dir.close().expect("Delete test directory");
+  }
+
+  #[test]
+  fn serves_directory_index_with_live_reload() {
+    let (temp_dir, address, _) = start_test_server();
+
+    let root_response = request(address, "/");
+    let posts_response = request(address, "/posts/?page=1");
+
+    assert!(root_response.contains("Home<script>"));
+    assert!(root_response.ends_with("</script>\n</body></html>"));
+    assert!(posts_response.contains("<p>Posts</p><script>"));
+
+    temp_dir.close().expect("Delete test directory");
+  }
+
+  #[test]
+  fn serves_not_found_page() {
+    let (temp_dir, address, _) = start_test_server();
+
+    let missing_response = request(address, "/missing.html");
+    let outside_response = request(address, "/../secret.txt");
+    fs::write(temp_dir.path().join("404.html"), "<p>Custom</p>").expect("Create test file");
+    let custom_response = request(address, "/missing.html");
+
+    assert!(missing_response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    assert!(outside_response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    assert!(custom_response.contains("<p>Custom</p>"));
+
+    temp_dir.close().expect("Delete test directory");
+  }
+
+  #[test]
+  fn reports_reload_version() {
+    let (temp_dir, address, reload_version) = start_test_server();
+
+    assert!(request(address, RELOAD_PATH).ends_with("\r\n\r\n0"));
+    reload_version.fetch_add(1, Ordering::SeqCst);
+    assert!(request(address, RELOAD_PATH).ends_with("\r\n\r\n1"));
+
+    temp_dir.close().expect("Delete test directory");
+  }
+}
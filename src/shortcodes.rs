@@ -0,0 +1,202 @@
+use std::collections::{BTreeMap, HashMap};
+use std::{fs, path::Path};
+
+use crate::document::Shortcode;
+use crate::{escape_html_attribute, Error};
+
+const PLACEHOLDER_START: &str = "{{";
+const PLACEHOLDER_END: &str = "}}";
+// Placeholder in a shortcode template replaced with the shortcode's body
+const BODY_PLACEHOLDER: &str = "body";
+
+/// A shortcode tag found on a line, before it's matched with a template
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Tag {
+  pub name: String,
+  pub args: Vec<String>,
+  pub named_args: BTreeMap<String, String>,
+  /// `{% name %}` tags wrap a body up to their `{% endname %}` tag, while
+  /// `{{< name >}}` tags stand alone
+  pub paired: bool,
+}
+
+/// Read every .html file in a directory as a shortcode template, named after
+/// the file
+pub fn read_templates(dir_path: &Path) -> Result<HashMap<String, String>, Error> {
+  let mut templates = HashMap::new();
+  let dir = fs::read_dir(dir_path).map_err(|cause| Error::io("read shortcodes directory", dir_path, cause))?;
+
+  for entry in dir {
+    let path = entry
+      .map_err(|cause| Error::io("read shortcodes directory", dir_path, cause))?
+      .path();
+    if path.extension().is_some_and(|extension| extension == "html") {
+      let name = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+      templates.insert(name, crate::error::read_text_file("read shortcode", &path)?);
+    }
+  }
+
+  Ok(templates)
+}
+
+/// returns None if the line isn't a shortcode tag on its own
+pub(crate) fn parse_tag(line: &str) -> Option<Tag> {
+  let line = line.trim();
+  let (inside, paired) = if let Some(inside) = line.strip_prefix("{{<").and_then(|rest| rest.strip_suffix(">}}")) {
+    (inside, false)
+  } else {
+    (line.strip_prefix("{%")?.strip_suffix("%}")?, true)
+  };
+
+  let mut words = split_args(inside).into_iter();
+  let name = words.next()?;
+  let mut args = Vec::new();
+  let mut named_args = BTreeMap::new();
+  for word in words {
+    match word.split_once('=') {
+      Some((key, value)) if !key.is_empty() => {
+        named_args.insert(key.to_string(), unquote(value));
+      }
+      _ => args.push(unquote(&word)),
+    }
+  }
+
+  Some(Tag {
+    name,
+    args,
+    named_args,
+    paired,
+  })
+}
+
+// Split on spaces outside double quotes, keeping the quotes so they can be
+// removed from values later
+fn split_args(text: &str) -> Vec<String> {
+  let mut words = Vec::new();
+  let mut word = String::new();
+  let mut in_quotes = false;
+  let mut escaped = false;
+
+  for char in text.chars() {
+    if char.is_whitespace() && !in_quotes {
+      if !word.is_empty() {
+        words.push(std::mem::take(&mut word));
+      }
+      continue;
+    }
+    if char == '"' && !escaped {
+      in_quotes = !in_quotes;
+    }
+    escaped = char == '\\' && !escaped;
+    word.push(char);
+  }
+
+  if !word.is_empty() {
+    words.push(word);
+  }
+  words
+}
+
+fn unquote(value: &str) -> String {
+  match value.strip_prefix('"').and_then(|value| value.strip_suffix('"')) {
+    Some(value) => value.replace("\\\"", "\""),
+    None => value.to_string(),
+  }
+}
+
+/// Fill in a shortcode template, where `{{0}}`, `{{1}}`... are positional
+/// arguments, `{{key}}` are named arguments and `{{body}}` is the rendered
+/// body
+/// Arguments are escaped so they can be used in attributes, and placeholders
+/// without a value are left empty
+pub(crate) fn expand(template: &str, shortcode: &Shortcode, body: &str) -> String {
+  let mut expanded = String::with_capacity(template.len());
+  let mut rest = template;
+
+  while let Some(start) = rest.find(PLACEHOLDER_START) {
+    let key_start = start + PLACEHOLDER_START.len();
+    let key_length = match rest[key_start..].find(PLACEHOLDER_END) {
+      Some(key_length) => key_length,
+      None => break,
+    };
+
+    expanded.push_str(&rest[..start]);
+    let key = rest[key_start..(key_start + key_length)].trim();
+    if key == BODY_PLACEHOLDER {
+      expanded.push_str(body);
+    } else {
+      let value = match key.parse::<usize>() {
+        Ok(index) => shortcode.args.get(index),
+        Err(_) => shortcode.named_args.get(key),
+      };
+      expanded.push_str(&escape_html_attribute(value.map_or("", String::as_str)));
+    }
+    rest = &rest[(key_start + key_length + PLACEHOLDER_END.len())..];
+  }
+
+  expanded.push_str(rest);
+  expanded
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::document::Span;
+  use crate::shortcodes::*;
+
+  #[test]
+  fn parses_standalone_and_paired_tags() {
+    assert_eq!(
+      parse_tag("{{< youtube dQw4w9WgXcQ start=30 >}}"),
+      Some(Tag {
+        name: "youtube".to_string(),
+        args: vec!["dQw4w9WgXcQ".to_string()],
+        named_args: BTreeMap::from([("start".to_string(), "30".to_string())]),
+        paired: false,
+      })
+    );
+
+    let tag = parse_tag("  {% figure \"cat.png\" caption=\"A \\\"sleepy\\\" cat\" %}\n").unwrap();
+    assert_eq!(tag.name, "figure");
+    assert_eq!(tag.args, ["cat.png"]);
+    assert_eq!(tag.named_args["caption"], "A \"sleepy\" cat");
+    assert!(tag.paired);
+
+    assert_eq!(parse_tag("{% endnote %}").unwrap().name, "endnote");
+    assert_eq!(parse_tag("Some {{< youtube id >}} text"), None);
+    assert_eq!(parse_tag("{{<  >}}"), None);
+  }
+
+  #[test]
+  fn expands_templates() {
+    let shortcode = Shortcode {
+      name: "note".to_string(),
+      args: vec!["warning".to_string()],
+      named_args: BTreeMap::from([("title".to_string(), "Read <this>".to_string())]),
+      body: None,
+      span: Span::default(),
+    };
+
+    assert_eq!(
+      expand(
+        "<aside class=\"{{0}}\" title=\"{{ title }}\">{{body}}{{1}}</aside>",
+        &shortcode,
+        "<p>Hi</p>"
+      ),
+      "<aside class=\"warning\" title=\"Read &lt;this&gt;\"><p>Hi</p></aside>"
+    );
+  }
+
+  #[test]
+  fn reads_templates_from_directory() {
+    let temp_dir = tempfile::tempdir().expect("Create test directory");
+    fs::write(temp_dir.path().join("note.html"), "<aside>{{body}}</aside>").expect("Create test file");
+    fs::write(temp_dir.path().join("README.md"), "Shortcodes").expect("Create test file");
+
+    let templates = read_templates(temp_dir.path()).unwrap();
+    assert_eq!(templates.len(), 1);
+    assert_eq!(templates["note"], "<aside>{{body}}</aside>");
+    assert!(read_templates(&temp_dir.path().join("missing")).is_err());
+
+    temp_dir.close().expect("Delete test directory");
+  }
+}
@@ -0,0 +1,268 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{fs, io, path};
+
+use crate::escape_html_attribute;
+
+const PLACEHOLDER_START: &str = "{{site.";
+const PLACEHOLDER_END: &str = "}}";
+// Pages that shouldn't be listed in the sitemap
+const SITEMAP_EXCLUDED_PAGES: [&str; 1] = ["404.html"];
+
+/// A link in the site's navigation menu
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct NavEntry {
+  pub title: String,
+  pub url: String,
+}
+
+/// Site wide values from the config, shared by every page
+#[derive(Debug, Default)]
+pub struct SiteMetadata {
+  pub title: Option<String>,
+  pub base_url: Option<String>,
+  pub author: Option<String>,
+  pub description: Option<String>,
+  pub favicon: Option<String>,
+  pub copyright: Option<String>,
+  pub nav: Vec<NavEntry>,
+  pub extra: Map<String, Value>,
+}
+
+impl SiteMetadata {
+  /// Replace `{{site.KEY}}` and `{{site.extra.KEY}}` placeholders in a
+  /// template, using an empty string for values that aren't set
+  pub fn fill_template(&self, template: &str) -> String {
+    let mut filled = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find(PLACEHOLDER_START) {
+      let key_start = start + PLACEHOLDER_START.len();
+      let key_length = match rest[key_start..].find(PLACEHOLDER_END) {
+        Some(key_length) => key_length,
+        None => break,
+      };
+
+      filled.push_str(&rest[..start]);
+      filled.push_str(&self.value(rest[key_start..(key_start + key_length)].trim()));
+      rest = &rest[(key_start + key_length + PLACEHOLDER_END.len())..];
+    }
+
+    filled.push_str(rest);
+    filled
+  }
+
+  /// Description, author, favicon and canonical link tags for the head of a
+  /// page
+  pub fn meta_tags(&self, page_file_name: Option<&str>, page_author: Option<&str>) -> String {
+    let mut tags = String::new();
+
+    if let Some(description) = &self.description {
+      tags.push_str(&format!(
+        "\t<meta name=\"description\" content=\"{}\">\n",
+        escape_html_attribute(description)
+      ));
+    }
+    if let Some(author) = page_author.or(self.author.as_deref()) {
+      tags.push_str(&format!(
+        "\t<meta name=\"author\" content=\"{}\">\n",
+        escape_html_attribute(author)
+      ));
+    }
+    if let Some(favicon) = &self.favicon {
+      tags.push_str(&format!(
+        "\t<link rel=\"icon\" href=\"{}\">\n",
+        escape_html_attribute(favicon)
+      ));
+    }
+    if let Some(page_url) = page_file_name.and_then(|page_file_name| self.page_url(page_file_name)) {
+      tags.push_str(&format!(
+        "\t<link rel=\"canonical\" href=\"{}\">\n",
+        escape_html_attribute(&page_url)
+      ));
+    }
+
+    tags
+  }
+
+  /// The absolute url of a page in the output directory, if the base url is
+  /// set
+  pub fn page_url(&self, page_path: &str) -> Option<String> {
+    let base_url = self.base_url.as_deref()?.trim_end_matches('/');
+    Some(format!("{base_url}/{}", percent_encode(page_path)))
+  }
+
+  fn value(&self, key: &str) -> String {
+    let text = match key {
+      "title" => self.title.as_deref(),
+      "base_url" => self.base_url.as_deref(),
+      "author" => self.author.as_deref(),
+      "description" => self.description.as_deref(),
+      "favicon" => self.favicon.as_deref(),
+      "copyright" => self.copyright.as_deref(),
+      "nav" => return render_nav(&self.nav),
+      _ => {
+        return match key
+          .strip_prefix("extra.")
+          .and_then(|extra_key| self.extra.get(extra_key))
+        {
+          Some(Value::String(text)) => escape_html_attribute(text),
+          Some(value) => escape_html_attribute(&value.to_string()),
+          None => String::new(),
+        }
+      }
+    };
+
+    text.map(escape_html_attribute).unwrap_or_default()
+  }
+}
+
+/// Render the navigation menu as a list of links, or nothing if it's empty
+pub fn render_nav(nav: &[NavEntry]) -> String {
+  if nav.is_empty() {
+    return String::new();
+  }
+
+  let mut html = String::from("<nav class=\"site-nav\">\n<ul>\n");
+  for entry in nav {
+    html.push_str(&format!(
+      "<li><a href=\"{}\">{}</a></li>\n",
+      escape_html_attribute(&entry.url),
+      escape_html_attribute(&entry.title)
+    ));
+  }
+  html.push_str("</ul>\n</nav>\n");
+  html
+}
+
+/// Write a sitemap.xml listing every html page in the output directory,
+/// returning the number of pages listed
+pub fn write_sitemap(output_dir: &path::Path, site: &SiteMetadata) -> io::Result<usize> {
+  let mut pages = Vec::new();
+  collect_pages(output_dir, output_dir, &mut pages)?;
+  pages.sort();
+
+  let mut sitemap = String::from(
+    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+  );
+  for page in &pages {
+    if let Some(page_url) = site.page_url(page) {
+      sitemap.push_str(&format!(
+        "  <url>\n    <loc>{}</loc>\n  </url>\n",
+        escape_html_attribute(&page_url)
+      ));
+    }
+  }
+  sitemap.push_str("</urlset>\n");
+
+  fs::write(output_dir.join("sitemap.xml"), sitemap)?;
+  Ok(pages.len())
+}
+
+// Every byte apart from letters, digits, `-._~` and `/` is written as %XX
+fn percent_encode(path: &str) -> String {
+  let mut encoded = String::with_capacity(path.len());
+  for byte in path.bytes() {
+    match byte {
+      b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => encoded.push(byte as char),
+      _ => encoded.push_str(&format!("%{byte:02X}")),
+    }
+  }
+  encoded
+}
+
+// Paths of html pages relative to the output directory, using / separators
+fn collect_pages(output_dir: &path::Path, dir: &path::Path, pages: &mut Vec<String>) -> io::Result<()> {
+  for entry in fs::read_dir(dir)? {
+    let path = entry?.path();
+    if path.is_dir() {
+      collect_pages(output_dir, &path, pages)?;
+    } else if path.extension().is_some_and(|extension| extension == "html") {
+      let relative_path = path.strip_prefix(output_dir).unwrap_or(&path);
+      let page: Vec<String> = relative_path
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().to_string())
+        .collect();
+      let page = page.join("/");
+      if !SITEMAP_EXCLUDED_PAGES.contains(&page.as_str()) {
+        pages.push(page);
+      }
+    }
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::site::*;
+
+  fn site() -> SiteMetadata {
+    SiteMetadata {
+      title: Some("Sherlock & Co".to_string()),
+      base_url: Some("https://example.com/stories/".to_string()),
+      author: Some("Arthur Conan Doyle".to_string()),
+      nav: vec![NavEntry {
+        title: "Home".to_string(),
+        url: "index.html".to_string(),
+      }],
+      extra: Map::from_iter([
+        ("theme".to_string(), Value::from("dark")),
+        ("year".to_string(), Value::from(1892)),
+      ]),
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn fills_site_placeholders() {
+    let template = "<title>{{title}} | {{site.title}}</title>{{site.nav}}<body \
+                    class=\"{{site.extra.theme}}\">{{site.extra.year}}{{site.description}}{{site.unknown}}";
+
+    assert_eq!(
+      site().fill_template(template),
+      "<title>{{title}} | Sherlock &amp; Co</title><nav class=\"site-nav\">\n<ul>\n<li><a \
+       href=\"index.html\">Home</a></li>\n</ul>\n</nav>\n<body class=\"dark\">1892"
+    );
+  }
+
+  #[test]
+  fn creates_meta_tags() {
+    assert_eq!(
+      site().meta_tags(Some("Silver Blaze.html"), None),
+      "\t<meta name=\"author\" content=\"Arthur Conan Doyle\">\n\t<link rel=\"canonical\" \
+       href=\"https://example.com/stories/Silver%20Blaze.html\">\n"
+    );
+    assert_eq!(
+      SiteMetadata::default().meta_tags(None, Some("Someone")),
+      "\t<meta name=\"author\" content=\"Someone\">\n"
+    );
+  }
+
+  #[test]
+  fn percent_encodes_page_urls() {
+    assert_eq!(
+      site().page_url("posts/Q&A #1?é.html").as_deref(),
+      Some("https://example.com/stories/posts/Q%26A%20%231%3F%C3%A9.html")
+    );
+    assert_eq!(SiteMetadata::default().page_url("index.html"), None);
+  }
+
+  #[test]
+  fn writes_sitemap_of_pages() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    fs::create_dir_all(temp_dir.path().join("posts")).expect("Create test directory");
+    for page in ["index.html", "404.html", "posts/first.html", "styles.css"] {
+      fs::write(temp_dir.path().join(page), "").expect("Create test page");
+    }
+
+    assert_eq!(write_sitemap(temp_dir.path(), &site()).unwrap(), 2);
+    let sitemap = fs::read_to_string(temp_dir.path().join("sitemap.xml")).unwrap();
+    assert!(sitemap.contains("<loc>https://example.com/stories/index.html</loc>"));
+    assert!(sitemap.contains("<loc>https://example.com/stories/posts/first.html</loc>"));
+    assert!(!sitemap.contains("404.html"));
+
+    temp_dir.close().expect("Delete test directory");
+  }
+}
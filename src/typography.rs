@@ -0,0 +1,212 @@
+// Elements whose text is left as is: code, math, and elements whose text
+// isn't prose, like scripts
+const SKIPPED_ELEMENTS: [&str; 6] = ["code", "pre", "math", "script", "style", "textarea"];
+
+/// Opening and closing quote characters used by a language
+struct Quotes {
+  double_open: &'static str,
+  double_close: &'static str,
+  single_open: &'static str,
+  single_close: &'static str,
+}
+
+impl Quotes {
+  fn for_lang(lang: &str) -> Quotes {
+    let language = lang.split(['-', '_']).next().unwrap_or_default().to_lowercase();
+
+    match language.as_str() {
+      // French puts a no-break space inside guillemets
+      "fr" => Quotes {
+        double_open: "«\u{a0}",
+        double_close: "\u{a0}»",
+        single_open: "‹\u{a0}",
+        single_close: "\u{a0}›",
+      },
+      "de" => Quotes {
+        double_open: "„",
+        double_close: "“",
+        single_open: "‚",
+        single_close: "‘",
+      },
+      "es" | "it" | "pt" | "ru" => Quotes {
+        double_open: "«",
+        double_close: "»",
+        single_open: "“",
+        single_close: "”",
+      },
+      _ => Quotes {
+        double_open: "“",
+        double_close: "”",
+        single_open: "‘",
+        single_close: "’",
+      },
+    }
+  }
+}
+
+/// Replace straight quotes, `--`, `---` and `...` in html with typographic
+/// characters for the given language, leaving tags, `<code>`, `<pre>`, math,
+/// `<script>`, `<style>` and `<textarea>` elements and `backtick` spans
+/// untouched
+pub fn smarten(html: &str, lang: &str) -> String {
+  let quotes = Quotes::for_lang(lang);
+  let chars: Vec<char> = html.chars().collect();
+  let mut smartened = String::with_capacity(html.len());
+  let mut previous_char: Option<char> = None;
+  let mut single_quote_open = false;
+  let mut code_depth = 0;
+  let mut i = 0;
+
+  while i < chars.len() {
+    let char = chars[i];
+
+    // Copy tags as is, keeping track of whether we're inside code or math
+    if char == '<' {
+      if let Some(tag_length) = chars[i..].iter().position(|&char| char == '>') {
+        let tag: String = chars[i..=(i + tag_length)].iter().collect();
+        code_depth = skipped_depth(&tag, code_depth);
+
+        smartened.push_str(&tag);
+        i += tag_length + 1;
+        continue;
+      }
+    }
+
+    if code_depth > 0 {
+      smartened.push(char);
+      i += 1;
+      continue;
+    }
+
+    // Copy backtick code spans as is
+    if char == '`' {
+      if let Some(span_length) = chars[(i + 1)..].iter().position(|&char| char == '`') {
+        smartened.extend(&chars[i..=(i + span_length + 1)]);
+        previous_char = Some('`');
+        i += span_length + 2;
+        continue;
+      }
+    }
+
+    let next_char = chars.get(i + 1).copied();
+    let next_next_char = chars.get(i + 2).copied();
+
+    match char {
+      '.' if next_char == Some('.') && next_next_char == Some('.') => {
+        smartened.push('…');
+        i += 3;
+      }
+      '-' if next_char == Some('-') && next_next_char == Some('-') => {
+        smartened.push('—');
+        i += 3;
+      }
+      '-' if next_char == Some('-') => {
+        smartened.push('–');
+        i += 2;
+      }
+      '"' => {
+        smartened.push_str(if opens_quote(previous_char) {
+          quotes.double_open
+        } else {
+          quotes.double_close
+        });
+        i += 1;
+      }
+      // Quotes inside words, or after a letter with no quotation open, are
+      // apostrophes e.g. don't, Holmes'
+      '\''
+        if previous_char.is_some_and(|char| char.is_alphanumeric())
+          && (!single_quote_open || next_char.is_some_and(|char| char.is_alphanumeric())) =>
+      {
+        smartened.push('’');
+        i += 1;
+      }
+      '\'' => {
+        single_quote_open = opens_quote(previous_char);
+        smartened.push_str(if single_quote_open {
+          quotes.single_open
+        } else {
+          quotes.single_close
+        });
+        i += 1;
+      }
+      _ => {
+        smartened.push(char);
+        i += 1;
+      }
+    }
+
+    previous_char = Some(char);
+  }
+
+  smartened
+}
+
+/// The number of elements whose text is left as is that are open after a tag,
+/// given the number open before it
+pub(crate) fn skipped_depth(tag: &str, depth: usize) -> usize {
+  let tag = tag.trim_start_matches('<');
+  let (closing, tag) = match tag.strip_prefix('/') {
+    Some(tag) => (true, tag),
+    None => (false, tag),
+  };
+  let name = tag
+    .split(|char: char| char.is_whitespace() || char == '>' || char == '/')
+    .next()
+    .unwrap_or_default()
+    .to_lowercase();
+
+  match SKIPPED_ELEMENTS.contains(&name.as_str()) {
+    true if closing => depth.saturating_sub(1),
+    true => depth + 1,
+    false => depth,
+  }
+}
+
+// Quotes at the start of text or after whitespace or opening punctuation open
+// a quotation
+fn opens_quote(previous_char: Option<char>) -> bool {
+  previous_char.is_none_or(|char| char.is_whitespace() || "([{-–—\"'“‘«„‚‹".contains(char))
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::typography::*;
+
+  #[test]
+  fn converts_quotes_dashes_and_ellipses() {
+    assert_eq!(
+      smarten("\"Go! Where to?\" -- Holmes' reply... 'Dartmoor' --- don't", "en-CA"),
+      "“Go! Where to?” – Holmes’ reply… ‘Dartmoor’ — don’t"
+    );
+  }
+
+  #[test]
+  fn uses_quotes_for_lang() {
+    assert_eq!(smarten("\"Bonjour\"", "fr"), "«\u{a0}Bonjour\u{a0}»");
+    assert_eq!(smarten("\"Hallo\" 'du'", "de-DE"), "„Hallo“ ‚du‘");
+  }
+
+  #[test]
+  fn leaves_tags_and_code_untouched() {
+    let html = "<a href=\"a--b.html\">\"link\"</a> `x -- y` <code>\"z\"...</code> \"done\"";
+    assert_eq!(
+      smarten(html, "en"),
+      "<a href=\"a--b.html\">“link”</a> `x -- y` <code>\"z\"...</code> “done”"
+    );
+  }
+
+  #[test]
+  fn leaves_scripts_and_styles_untouched() {
+    let html = "<script>var s = \"a\" -- 1;</script>\n<STYLE>q::before { content: '...' }</STYLE>\n<textarea \
+                rows=\"2\">\"x\"</textarea> <scripts>\"y\"</scripts>";
+    assert_eq!(
+      smarten(html, "en"),
+      "<script>var s = \"a\" -- 1;</script>\n<STYLE>q::before { content: '...' }</STYLE>\n<textarea \
+       rows=\"2\">\"x\"</textarea> <scripts>“y”</scripts>"
+    );
+    assert_eq!(skipped_depth("<code class=\"math\">", 0), 1);
+    assert_eq!(skipped_depth("</pre>", 0), 0);
+    assert_eq!(skipped_depth("<pretty>", 1), 1);
+  }
+}
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+use std::{fs, path, thread};
+
+// How often watched paths are checked for changes
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+// How long files must stay unchanged before a burst of saves is considered
+// finished
+const DEBOUNCE_INTERVAL: Duration = Duration::from_millis(300);
+
+/// Modification times of every watched file
+pub type Snapshot = HashMap<path::PathBuf, SystemTime>;
+
+/// Files that were added, changed or removed between two snapshots
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Changes {
+  pub modified: Vec<path::PathBuf>,
+  pub removed: Vec<path::PathBuf>,
+}
+
+/// Record the modification time of each watched file, and of the files
+/// directly inside each watched directory
+pub fn take_snapshot(watched_paths: &[path::PathBuf]) -> Snapshot {
+  let mut snapshot = Snapshot::new();
+
+  for watched_path in watched_paths {
+    if watched_path.is_dir() {
+      for entry in fs::read_dir(watched_path).into_iter().flatten().flatten() {
+        record_modified_time(&mut snapshot, &entry.path());
+      }
+    } else {
+      record_modified_time(&mut snapshot, watched_path);
+    }
+  }
+
+  snapshot
+}
+
+fn record_modified_time(snapshot: &mut Snapshot, file_path: &path::Path) {
+  if let Ok(modified) = fs::metadata(file_path).and_then(|metadata| metadata.modified()) {
+    if file_path.is_file() {
+      snapshot.insert(file_path.to_path_buf(), modified);
+    }
+  }
+}
+
+pub fn diff_snapshots(previous: &Snapshot, current: &Snapshot) -> Changes {
+  let mut changes = Changes::default();
+
+  for (file_path, modified) in current {
+    if previous.get(file_path) != Some(modified) {
+      changes.modified.push(file_path.clone());
+    }
+  }
+
+  for file_path in previous.keys() {
+    if !current.contains_key(file_path) {
+      changes.removed.push(file_path.clone());
+    }
+  }
+
+  changes.modified.sort();
+  changes.removed.sort();
+  changes
+}
+
+/// Block until the watched paths change, waiting for a burst of saves to
+/// settle before returning what changed
+pub fn wait_for_changes(watched_paths: &[path::PathBuf], snapshot: &mut Snapshot) -> Changes {
+  loop {
+    thread::sleep(POLL_INTERVAL);
+    let mut current = take_snapshot(watched_paths);
+    if current == *snapshot {
+      continue;
+    }
+
+    loop {
+      thread::sleep(DEBOUNCE_INTERVAL);
+      let settled = take_snapshot(watched_paths);
+      if settled == current {
+        break;
+      }
+      current = settled;
+    }
+
+    let changes = diff_snapshots(snapshot, &current);
+    *snapshot = current;
+    return changes;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs;
+
+  use crate::watch::*;
+
+  #[test]
+  fn snapshots_files_in_watched_directories() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let nested_dir = temp_dir.path().join("nested");
+    fs::create_dir(&nested_dir).expect("Create test directory");
+    fs::write(temp_dir.path().join("page.md"), "# Page").expect("Create test file");
+    fs::write(nested_dir.join("ignored.md"), "# Ignored").expect("Create test file");
+
+    let snapshot = take_snapshot(&[temp_dir.path().to_path_buf()]);
+
+    assert_eq!(snapshot.len(), 1);
+    assert!(snapshot.contains_key(&temp_dir.path().join("page.md")));
+
+    temp_dir.close().expect("Delete test directory");
+  }
+
+  #[test]
+  fn diffs_modified_and_removed_files() {
+    let now = SystemTime::now();
+    let later = now + Duration::from_secs(1);
+    let previous = Snapshot::from([
+      (path::PathBuf::from("kept.md"), now),
+      (path::PathBuf::from("changed.md"), now),
+      (path::PathBuf::from("removed.md"), now),
+    ]);
+    let current = Snapshot::from([
+      (path::PathBuf::from("kept.md"), now),
+      (path::PathBuf::from("changed.md"), later),
+      (path::PathBuf::from("added.md"), now),
+    ]);
+
+    let changes = diff_snapshots(&previous, &current);
+
+    assert_eq!(
+      changes,
+      Changes {
+        modified: vec![path::PathBuf::from("added.md"), path::PathBuf::from("changed.md")],
+        removed: vec![path::PathBuf::from("removed.md")],
+      }
+    );
+  }
+}